@@ -0,0 +1,11 @@
+CREATE TABLE bookmarks(
+    message_id      INTEGER PRIMARY KEY,
+    annotation      TEXT    NOT NULL,
+    created         INTEGER NOT NULL
+);
+-- messages has no declared key for a foreign key to reference, so deleting a message
+-- removes its bookmark here instead of through ON DELETE CASCADE
+CREATE TRIGGER bookmarks_message_deleted AFTER DELETE ON messages
+BEGIN
+    DELETE FROM bookmarks WHERE message_id = OLD.rowid;
+END;
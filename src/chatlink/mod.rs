@@ -0,0 +1,537 @@
+//! Decoding of Guild Wars 2 chat links, the `[&...]` codes players paste into chat.
+//! The payload is base64, starting with a header byte giving the type of link.
+//! See <https://wiki.guildwars2.com/wiki/Chat_link_format> for the layouts.
+
+use std::ops::Range;
+
+use anyhow::{bail, Context};
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+pub mod names;
+
+static CHAT_LINK_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"\[&([A-Za-z0-9+/]+=*)\]").unwrap());
+
+const HEADER_COIN: u8 = 0x01;
+const HEADER_ITEM: u8 = 0x02;
+const HEADER_NPC_TEXT: u8 = 0x03;
+const HEADER_MAP: u8 = 0x04;
+const HEADER_SKILL: u8 = 0x06;
+const HEADER_TRAIT: u8 = 0x07;
+const HEADER_RECIPE: u8 = 0x09;
+const HEADER_WARDROBE: u8 = 0x0A;
+const HEADER_OUTFIT: u8 = 0x0B;
+const HEADER_WVW_OBJECTIVE: u8 = 0x0C;
+const HEADER_BUILD_TEMPLATE: u8 = 0x0D;
+
+const ITEM_FLAG_SKIN: u8 = 0x80;
+const ITEM_FLAG_UPGRADE_1: u8 = 0x40;
+const ITEM_FLAG_UPGRADE_2: u8 = 0x20;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChatLink {
+    /// An amount of copper.
+    Coin(u32),
+    Item(ItemLink),
+    NpcText(u32),
+    /// A waypoint, point of interest or vista.
+    Map(u32),
+    Skill(u32),
+    Trait(u32),
+    Recipe(u32),
+    /// A skin from the wardrobe.
+    Wardrobe(u32),
+    Outfit(u32),
+    WvwObjective {
+        objective_id: u32,
+        map_id: u32,
+    },
+    BuildTemplate(BuildTemplate),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ItemLink {
+    pub count: u8,
+    pub id: u32,
+    /// Transmuted skin, if different from the item's default.
+    pub skin: Option<u32>,
+    /// Upgrade components and infusions, in slot order.
+    pub upgrades: Vec<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Profession {
+    Guardian,
+    Warrior,
+    Engineer,
+    Ranger,
+    Thief,
+    Elementalist,
+    Mesmer,
+    Necromancer,
+    Revenant,
+}
+
+impl Profession {
+    fn from_code(code: u8) -> Option<Self> {
+        Some(match code {
+            1 => Self::Guardian,
+            2 => Self::Warrior,
+            3 => Self::Engineer,
+            4 => Self::Ranger,
+            5 => Self::Thief,
+            6 => Self::Elementalist,
+            7 => Self::Mesmer,
+            8 => Self::Necromancer,
+            9 => Self::Revenant,
+            _ => return None,
+        })
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Guardian => "Guardian",
+            Self::Warrior => "Warrior",
+            Self::Engineer => "Engineer",
+            Self::Ranger => "Ranger",
+            Self::Thief => "Thief",
+            Self::Elementalist => "Elementalist",
+            Self::Mesmer => "Mesmer",
+            Self::Necromancer => "Necromancer",
+            Self::Revenant => "Revenant",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Specialization {
+    pub id: u8,
+    /// Chosen trait in the adept, master and grandmaster tiers:
+    /// 0 for none, then 1 to 3 from top to bottom.
+    pub traits: [u8; 3],
+}
+
+/// Data only present for professions with pets or legends.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProfessionData {
+    None,
+    Ranger {
+        terrestrial_pets: [u8; 2],
+        aquatic_pets: [u8; 2],
+    },
+    Revenant {
+        terrestrial_legends: [u8; 2],
+        aquatic_legends: [u8; 2],
+        /// Utility skills of the inactive legends, as skill palette ids.
+        inactive_terrestrial_utilities: [u16; 3],
+        inactive_aquatic_utilities: [u16; 3],
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildTemplate {
+    pub profession: Profession,
+    pub specializations: [Specialization; 3],
+    /// Heal, three utilities and elite, as skill palette ids. 0 is an empty slot.
+    pub terrestrial_skills: [u16; 5],
+    pub aquatic_skills: [u16; 5],
+    pub profession_data: ProfessionData,
+    /// Weapon types selected for weapon mastery, only present in newer links.
+    pub weapons: Vec<u16>,
+    /// Skills replacing the defaults, such as chosen weapon skills, only present in newer links.
+    pub skill_overrides: Vec<u32>,
+}
+
+impl ChatLink {
+    /// Decodes a chat link, with or without the surrounding `[&` and `]`.
+    pub fn parse(code: &str) -> anyhow::Result<Self> {
+        let code = code.trim();
+        let payload = code
+            .strip_prefix("[&")
+            .and_then(|code| code.strip_suffix(']'))
+            .unwrap_or(code);
+        let data = decode_base64(payload).context("chat link is not valid base64")?;
+        Self::from_bytes(&data)
+    }
+
+    pub fn from_bytes(data: &[u8]) -> anyhow::Result<Self> {
+        let mut reader = Reader::new(data);
+        let header = reader.u8().context("chat link is empty")?;
+        let link = match header {
+            HEADER_COIN => Self::Coin(reader.u32()?),
+            HEADER_ITEM => Self::Item(ItemLink::read(&mut reader)?),
+            HEADER_NPC_TEXT => Self::NpcText(reader.u32()?),
+            HEADER_MAP => Self::Map(reader.u32()?),
+            HEADER_SKILL => Self::Skill(reader.u32()?),
+            HEADER_TRAIT => Self::Trait(reader.u32()?),
+            HEADER_RECIPE => Self::Recipe(reader.u32()?),
+            HEADER_WARDROBE => Self::Wardrobe(reader.u32()?),
+            HEADER_OUTFIT => Self::Outfit(reader.u32()?),
+            HEADER_WVW_OBJECTIVE => Self::WvwObjective {
+                objective_id: reader.u32()?,
+                map_id: reader.u32()?,
+            },
+            HEADER_BUILD_TEMPLATE => Self::BuildTemplate(BuildTemplate::read(&mut reader)?),
+            _ => bail!("unsupported chat link type 0x{:02x}", header),
+        };
+        Ok(link)
+    }
+}
+
+impl ItemLink {
+    fn read(reader: &mut Reader) -> anyhow::Result<Self> {
+        let count = reader.u8()?;
+        // the id is 24 bits, with the flags saying what follows in the top byte
+        let id_and_flags = reader.u32()?;
+        let flags = (id_and_flags >> 24) as u8;
+        let mut item = Self {
+            count,
+            id: id_and_flags & 0xFF_FFFF,
+            skin: None,
+            upgrades: Vec::new(),
+        };
+        if flags & ITEM_FLAG_SKIN != 0 {
+            item.skin = Some(reader.u32()? & 0xFF_FFFF);
+        }
+        for flag in [ITEM_FLAG_UPGRADE_1, ITEM_FLAG_UPGRADE_2] {
+            if flags & flag != 0 {
+                item.upgrades.push(reader.u32()? & 0xFF_FFFF);
+            }
+        }
+        Ok(item)
+    }
+}
+
+impl BuildTemplate {
+    fn read(reader: &mut Reader) -> anyhow::Result<Self> {
+        let code = reader.u8()?;
+        let profession = Profession::from_code(code)
+            .with_context(|| format!("unknown profession code {}", code))?;
+        let mut specializations = [Specialization {
+            id: 0,
+            traits: [0; 3],
+        }; 3];
+        for specialization in &mut specializations {
+            specialization.id = reader.u8()?;
+            let traits = reader.u8()?;
+            specialization.traits = [traits & 3, (traits >> 2) & 3, (traits >> 4) & 3];
+        }
+        // terrestrial and aquatic skills alternate
+        let mut terrestrial_skills = [0; 5];
+        let mut aquatic_skills = [0; 5];
+        for i in 0..5 {
+            terrestrial_skills[i] = reader.u16()?;
+            aquatic_skills[i] = reader.u16()?;
+        }
+        let profession_bytes = reader.bytes(16)?;
+        let profession_data = match profession {
+            Profession::Ranger => ProfessionData::Ranger {
+                terrestrial_pets: [profession_bytes[0], profession_bytes[1]],
+                aquatic_pets: [profession_bytes[2], profession_bytes[3]],
+            },
+            Profession::Revenant => {
+                let mut inactive = Reader::new(&profession_bytes[4..]);
+                ProfessionData::Revenant {
+                    terrestrial_legends: [profession_bytes[0], profession_bytes[1]],
+                    aquatic_legends: [profession_bytes[2], profession_bytes[3]],
+                    inactive_terrestrial_utilities: [
+                        inactive.u16()?,
+                        inactive.u16()?,
+                        inactive.u16()?,
+                    ],
+                    inactive_aquatic_utilities: [inactive.u16()?, inactive.u16()?, inactive.u16()?],
+                }
+            }
+            _ => ProfessionData::None,
+        };
+        let mut template = Self {
+            profession,
+            specializations,
+            terrestrial_skills,
+            aquatic_skills,
+            profession_data,
+            weapons: Vec::new(),
+            skill_overrides: Vec::new(),
+        };
+        if !reader.is_empty() {
+            for _ in 0..reader.u8()? {
+                template.weapons.push(reader.u16()?);
+            }
+            for _ in 0..reader.u8()? {
+                template.skill_overrides.push(reader.u32()?);
+            }
+        }
+        Ok(template)
+    }
+}
+
+/// Finds the chat links in `text`, returning the byte range of each `[&...]` and its
+/// decoded link, or `None` if it couldn't be decoded.
+pub fn find_chat_links(text: &str) -> Vec<(Range<usize>, Option<ChatLink>)> {
+    CHAT_LINK_REGEX
+        .find_iter(text)
+        .map(|found| (found.range(), ChatLink::parse(found.as_str()).ok()))
+        .collect()
+}
+
+/// Formats an amount of copper the way the game does, e.g. `1g 2s 3c`.
+pub fn format_coins(copper: u32) -> String {
+    let gold = copper / 10000;
+    let silver = copper / 100 % 100;
+    let copper = copper % 100;
+    let mut parts = Vec::new();
+    if gold > 0 {
+        parts.push(format!("{}g", gold));
+    }
+    if silver > 0 {
+        parts.push(format!("{}s", silver));
+    }
+    if copper > 0 || parts.is_empty() {
+        parts.push(format!("{}c", copper));
+    }
+    parts.join(" ")
+}
+
+/// Little endian reader that fails instead of panicking on truncated links.
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    fn bytes(&mut self, len: usize) -> anyhow::Result<&'a [u8]> {
+        if self.data.len() < len {
+            bail!("chat link is truncated");
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> anyhow::Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> anyhow::Result<u16> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> anyhow::Result<u32> {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+}
+
+/// Decodes standard base64, with or without padding.
+fn decode_base64(input: &str) -> Option<Vec<u8>> {
+    fn value(c: u8) -> Option<u32> {
+        Some(match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        } as u32)
+    }
+
+    let input = input.trim_end_matches('=').as_bytes();
+    if input.is_empty() || input.len() % 4 == 1 {
+        return None;
+    }
+    let mut output = Vec::with_capacity(input.len() * 3 / 4);
+    for chunk in input.chunks(4) {
+        let mut bits = 0;
+        for (i, c) in chunk.iter().enumerate() {
+            bits |= value(*c)? << (18 - 6 * i);
+        }
+        let bytes = bits.to_be_bytes();
+        output.extend_from_slice(&bytes[1..chunk.len()]);
+    }
+    Some(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_base64() {
+        assert_eq!(
+            decode_base64("AdsnAAA="),
+            Some(vec![0x01, 0xdb, 0x27, 0, 0])
+        );
+        assert_eq!(decode_base64("AdsnAAA"), Some(vec![0x01, 0xdb, 0x27, 0, 0]));
+        assert_eq!(
+            decode_base64("AgGqtgAA"),
+            Some(vec![2, 1, 0xaa, 0xb6, 0, 0])
+        );
+        assert_eq!(decode_base64("B/IDAAA="), Some(vec![7, 0xf2, 3, 0, 0]));
+        assert_eq!(decode_base64("A"), None);
+        assert_eq!(decode_base64("Ag-B"), None);
+        assert_eq!(decode_base64(""), None);
+    }
+
+    #[test]
+    fn test_simple_links() {
+        assert_eq!(
+            ChatLink::parse("[&AdsnAAA=]").unwrap(),
+            ChatLink::Coin(10203)
+        );
+        assert_eq!(ChatLink::parse("[&BDAEAAA=]").unwrap(), ChatLink::Map(1072));
+        assert_eq!(
+            ChatLink::parse("[&BucCAAA=]").unwrap(),
+            ChatLink::Skill(743)
+        );
+        assert_eq!(
+            ChatLink::parse("[&B/IDAAA=]").unwrap(),
+            ChatLink::Trait(1010)
+        );
+        assert_eq!(ChatLink::parse("[&CQIAAAA=]").unwrap(), ChatLink::Recipe(2));
+        assert_eq!(
+            ChatLink::parse("[&CgEAAAA=]").unwrap(),
+            ChatLink::Wardrobe(1)
+        );
+        assert_eq!(ChatLink::parse("CwQAAAA=").unwrap(), ChatLink::Outfit(4));
+        assert_eq!(
+            ChatLink::parse("[&DAYAAAAmAAAA]").unwrap(),
+            ChatLink::WvwObjective {
+                objective_id: 6,
+                map_id: 38
+            }
+        );
+    }
+
+    #[test]
+    fn test_item_links() {
+        assert_eq!(
+            ChatLink::parse("[&AgGqtgAA]").unwrap(),
+            ChatLink::Item(ItemLink {
+                count: 1,
+                id: 46762,
+                skin: None,
+                upgrades: Vec::new(),
+            })
+        );
+        // skin and both upgrade slots
+        assert_eq!(
+            ChatLink::parse("[&AgGqtgDgrxYAACdgAAAqYAAA]").unwrap(),
+            ChatLink::Item(ItemLink {
+                count: 1,
+                id: 46762,
+                skin: Some(5807),
+                upgrades: vec![24615, 24618],
+            })
+        );
+        // a stack with only the first upgrade slot
+        assert_eq!(
+            ChatLink::parse("[&AvoJTQBA6l8AAA==]").unwrap(),
+            ChatLink::Item(ItemLink {
+                count: 250,
+                id: 19721,
+                skin: None,
+                upgrades: vec![24554],
+            })
+        );
+    }
+
+    #[test]
+    fn test_build_templates() {
+        let ChatLink::BuildTemplate(ranger) =
+            ChatLink::parse("[&DQQeJyAbNyp/FQAALhZ/FS8WAAAwFgAAZhYAADsuFBUAAAAAAAAAAAAAAAA=]")
+                .unwrap()
+        else {
+            panic!("not a build template");
+        };
+        assert_eq!(ranger.profession, Profession::Ranger);
+        assert_eq!(
+            ranger.specializations,
+            [
+                Specialization {
+                    id: 30,
+                    traits: [3, 1, 2]
+                },
+                Specialization {
+                    id: 32,
+                    traits: [3, 2, 1]
+                },
+                Specialization {
+                    id: 55,
+                    traits: [2, 2, 2]
+                },
+            ]
+        );
+        assert_eq!(ranger.terrestrial_skills, [5503, 5678, 5679, 5680, 5734]);
+        assert_eq!(ranger.aquatic_skills, [0, 5503, 0, 0, 0]);
+        assert_eq!(
+            ranger.profession_data,
+            ProfessionData::Ranger {
+                terrestrial_pets: [59, 46],
+                aquatic_pets: [20, 21],
+            }
+        );
+        assert!(ranger.weapons.is_empty());
+
+        let ChatLink::BuildTemplate(revenant) = ChatLink::parse(
+            "[&DQk0FQ8/PybcEdwRBhIGEisSKxLUEdQRyhHKEQ4NDg0GEisS1BEGEisS1BECWgAyAAEd9QAA]",
+        )
+        .unwrap() else {
+            panic!("not a build template");
+        };
+        assert_eq!(revenant.profession, Profession::Revenant);
+        assert_eq!(revenant.specializations[2].traits, [2, 1, 2]);
+        assert_eq!(revenant.terrestrial_skills, [4572, 4614, 4651, 4564, 4554]);
+        assert_eq!(
+            revenant.profession_data,
+            ProfessionData::Revenant {
+                terrestrial_legends: [14, 13],
+                aquatic_legends: [14, 13],
+                inactive_terrestrial_utilities: [4614, 4651, 4564],
+                inactive_aquatic_utilities: [4614, 4651, 4564],
+            }
+        );
+        assert_eq!(revenant.weapons, [90, 50]);
+        assert_eq!(revenant.skill_overrides, [62749]);
+    }
+
+    #[test]
+    fn test_invalid_links() {
+        // truncated item
+        assert!(ChatLink::parse("[&AgEB]").is_err());
+        // unknown header
+        assert!(ChatLink::from_bytes(&[0x7f, 1, 0, 0, 0]).is_err());
+        assert!(ChatLink::from_bytes(&[]).is_err());
+        // unknown profession
+        let mut template = vec![HEADER_BUILD_TEMPLATE, 10];
+        template.resize(44, 0);
+        assert!(ChatLink::from_bytes(&template).is_err());
+        template[1] = 1;
+        assert!(ChatLink::from_bytes(&template).is_ok());
+    }
+
+    #[test]
+    fn test_find_chat_links() {
+        let text = "buy [&AgGqtgAA] for [&AdsnAAA=] at [&BDAEAAA=], not [&] or [&AgEB]";
+        let links = find_chat_links(text);
+        assert_eq!(links.len(), 4);
+        assert_eq!(&text[links[0].0.clone()], "[&AgGqtgAA]");
+        assert_eq!(links[1].1, Some(ChatLink::Coin(10203)));
+        assert_eq!(links[2].1, Some(ChatLink::Map(1072)));
+        assert_eq!(links[3].1, None);
+    }
+
+    #[test]
+    fn test_format_coins() {
+        assert_eq!(format_coins(10203), "1g 2s 3c");
+        assert_eq!(format_coins(50000), "5g");
+        assert_eq!(format_coins(0), "0c");
+    }
+}
@@ -0,0 +1,256 @@
+use std::{collections::HashMap, fs};
+
+use anyhow::{bail, Context};
+use serde::Deserialize;
+
+use super::{format_coins, ChatLink, ProfessionData};
+
+/// Names shipped with the plugin, regenerated from the game's API by
+/// `tools/chat_link_names.py`. Anything else can be added through a user file in the
+/// same format.
+const BUNDLED_NAMES: &str = include_str!("../../data/chat_link_names.json");
+/// The newest file format this build understands. Version 2 added `build`.
+const FORMAT_VERSION: u32 = 2;
+
+/// Names for the ids in decoded chat links, keyed by id within each kind of link.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ChatLinkNames {
+    pub version: u32,
+    /// The game build the names were read from, if they were generated.
+    pub build: Option<u32>,
+    pub items: HashMap<u32, String>,
+    /// Waypoints, points of interest and vistas.
+    pub map: HashMap<u32, String>,
+    pub skills: HashMap<u32, String>,
+    pub traits: HashMap<u32, String>,
+    /// Wardrobe skins.
+    pub skins: HashMap<u32, String>,
+    pub outfits: HashMap<u32, String>,
+}
+
+impl ChatLinkNames {
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        let names: Self = serde_json::from_str(json).context("failed to parse chat link names")?;
+        if names.version > FORMAT_VERSION {
+            bail!(
+                "chat link names are version {}, but only up to version {} is supported",
+                names.version,
+                FORMAT_VERSION
+            );
+        }
+        Ok(names)
+    }
+
+    pub fn bundled() -> Self {
+        Self::from_json(BUNDLED_NAMES).expect("bundled chat link names are invalid")
+    }
+
+    /// Loads the bundled names, extended by the user file at `path` if it isn't empty.
+    /// Names in the user file replace bundled ones with the same id.
+    pub fn load(path: &str) -> anyhow::Result<Self> {
+        let mut names = Self::bundled();
+        if !path.is_empty() {
+            let json = fs::read_to_string(path)
+                .with_context(|| format!("failed to read chat link names from {}", path))?;
+            names.extend(Self::from_json(&json)?);
+        }
+        Ok(names)
+    }
+
+    pub fn extend(&mut self, other: Self) {
+        self.items.extend(other.items);
+        self.map.extend(other.map);
+        self.skills.extend(other.skills);
+        self.traits.extend(other.traits);
+        self.skins.extend(other.skins);
+        self.outfits.extend(other.outfits);
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+            + self.map.len()
+            + self.skills.len()
+            + self.traits.len()
+            + self.skins.len()
+            + self.outfits.len()
+    }
+
+    /// Returns the name of what the link points at, if it is known.
+    pub fn name(&self, link: &ChatLink) -> Option<String> {
+        match link {
+            ChatLink::Item(item) => {
+                let name = self.items.get(&item.id)?;
+                Some(match item.count {
+                    0 | 1 => name.clone(),
+                    count => format!("{} {}", count, name),
+                })
+            }
+            ChatLink::Map(id) => self.map.get(id).cloned(),
+            ChatLink::Skill(id) => self.skills.get(id).cloned(),
+            ChatLink::Trait(id) => self.traits.get(id).cloned(),
+            ChatLink::Wardrobe(id) => self.skins.get(id).cloned(),
+            ChatLink::Outfit(id) => self.outfits.get(id).cloned(),
+            _ => None,
+        }
+    }
+
+    /// A short label for the link, like the game shows in chat.
+    pub fn label(&self, link: &ChatLink) -> String {
+        if let Some(name) = self.name(link) {
+            return format!("[{}]", name);
+        }
+        match link {
+            ChatLink::Coin(copper) => format!("[{}]", format_coins(*copper)),
+            ChatLink::Item(item) if item.count > 1 => {
+                format!("[{} Item {}]", item.count, item.id)
+            }
+            ChatLink::BuildTemplate(template) => {
+                format!("[{} build]", template.profession.name())
+            }
+            link => format!("[{}]", self.describe_id(link)),
+        }
+    }
+
+    /// Everything decoded from the link, for showing in a tooltip.
+    pub fn details(&self, link: &ChatLink) -> String {
+        let mut lines = vec![self.describe_id(link)];
+        match link {
+            ChatLink::Coin(copper) => lines.push(format!("{} copper", copper)),
+            ChatLink::Item(item) => {
+                lines.push(format!("Count: {}", item.count));
+                if let Some(skin) = item.skin {
+                    lines.push(format!("Skin: {}", self.with_name(&self.skins, skin)));
+                }
+                for upgrade in &item.upgrades {
+                    lines.push(format!(
+                        "Upgrade: {}",
+                        self.with_name(&self.items, *upgrade)
+                    ));
+                }
+            }
+            ChatLink::BuildTemplate(template) => {
+                for specialization in &template.specializations {
+                    lines.push(format!(
+                        "Specialization {}: {}",
+                        specialization.id,
+                        itertools::join(specialization.traits, "-")
+                    ));
+                }
+                lines.push(format!(
+                    "Skills: {}",
+                    itertools::join(template.terrestrial_skills, ", ")
+                ));
+                lines.push(format!(
+                    "Aquatic skills: {}",
+                    itertools::join(template.aquatic_skills, ", ")
+                ));
+                match &template.profession_data {
+                    ProfessionData::Ranger {
+                        terrestrial_pets,
+                        aquatic_pets,
+                    } => {
+                        lines.push(format!("Pets: {}", itertools::join(terrestrial_pets, ", ")));
+                        lines.push(format!(
+                            "Aquatic pets: {}",
+                            itertools::join(aquatic_pets, ", ")
+                        ));
+                    }
+                    ProfessionData::Revenant {
+                        terrestrial_legends,
+                        aquatic_legends,
+                        ..
+                    } => {
+                        lines.push(format!(
+                            "Legends: {}",
+                            itertools::join(terrestrial_legends, ", ")
+                        ));
+                        lines.push(format!(
+                            "Aquatic legends: {}",
+                            itertools::join(aquatic_legends, ", ")
+                        ));
+                    }
+                    ProfessionData::None => {}
+                }
+                if !template.weapons.is_empty() {
+                    lines.push(format!(
+                        "Weapons: {}",
+                        itertools::join(&template.weapons, ", ")
+                    ));
+                }
+            }
+            _ => {}
+        }
+        lines.join("\n")
+    }
+
+    /// The kind of link and its id, with the name if it is known.
+    fn describe_id(&self, link: &ChatLink) -> String {
+        match link {
+            ChatLink::Coin(copper) => format_coins(*copper),
+            ChatLink::Item(item) => format!("Item {}", self.with_name(&self.items, item.id)),
+            ChatLink::NpcText(id) => format!("NPC text {}", id),
+            ChatLink::Map(id) => format!("Location {}", self.with_name(&self.map, *id)),
+            ChatLink::Skill(id) => format!("Skill {}", self.with_name(&self.skills, *id)),
+            ChatLink::Trait(id) => format!("Trait {}", self.with_name(&self.traits, *id)),
+            ChatLink::Recipe(id) => format!("Recipe {}", id),
+            ChatLink::Wardrobe(id) => format!("Skin {}", self.with_name(&self.skins, *id)),
+            ChatLink::Outfit(id) => format!("Outfit {}", self.with_name(&self.outfits, *id)),
+            ChatLink::WvwObjective {
+                objective_id,
+                map_id,
+            } => format!("WvW objective {}-{}", map_id, objective_id),
+            ChatLink::BuildTemplate(template) => {
+                format!("{} build template", template.profession.name())
+            }
+        }
+    }
+
+    fn with_name(&self, names: &HashMap<u32, String>, id: u32) -> String {
+        match names.get(&id) {
+            Some(name) => format!("{} ({})", id, name),
+            None => id.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_names() {
+        let mut names = ChatLinkNames::bundled();
+        assert!(names.len() > 0);
+        let ecto = ChatLink::parse("[&AvoJTQBA6l8AAA==]").unwrap();
+        assert_eq!(names.name(&ecto).as_deref(), Some("250 Glob of Ectoplasm"));
+        let waypoint = ChatLink::parse("[&BDAEAAA=]").unwrap();
+        assert_eq!(
+            names.name(&waypoint).as_deref(),
+            Some("Trader's Forum Waypoint (Lion's Arch)")
+        );
+
+        let user = ChatLinkNames::from_json(
+            r#"{"version": 1, "map": {"1072": "Somewhere Waypoint"}, "items": {"19721": "Ecto"}}"#,
+        )
+        .unwrap();
+        names.extend(user);
+        assert_eq!(
+            names.name(&ChatLink::Map(1072)).as_deref(),
+            Some("Somewhere Waypoint")
+        );
+        assert_eq!(names.name(&ecto).as_deref(), Some("250 Ecto"));
+        assert!(names.name(&ChatLink::Skill(1)).is_none());
+
+        assert_eq!(names.label(&ecto), "[250 Ecto]");
+        assert_eq!(names.label(&ChatLink::Coin(10203)), "[1g 2s 3c]");
+        assert_eq!(names.label(&ChatLink::Skill(1)), "[Skill 1]");
+        assert_eq!(
+            names.details(&ecto),
+            "Item 19721 (Ecto)\nCount: 250\nUpgrade: 24554"
+        );
+
+        assert!(ChatLinkNames::from_json(r#"{"version": 3}"#).is_err());
+        assert!(ChatLinkNames::from_json(r#"{"items": {"x": "y"}}"#).is_err());
+    }
+}
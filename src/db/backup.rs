@@ -0,0 +1,378 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, RecvTimeoutError},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, Context};
+use log::{error, info, warn};
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{
+    backup::{Backup, StepResult},
+    Connection,
+};
+use serde::{Deserialize, Serialize};
+
+use super::ChatDatabase;
+
+const DEFAULT_BACKUP_DIR: &str = "arcdps_chat_log_backups";
+const BACKUP_EXTENSION: &str = "db";
+const TMP_EXTENSION: &str = "tmp";
+const BACKUP_TIME_FORMAT: &str = "%Y%m%d-%H%M%S";
+/// How often the backup thread wakes up to check whether a scheduled backup is due.
+const BACKUP_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+const BACKUP_INTERVAL_SECONDS: i64 = 24 * 60 * 60;
+const BACKUP_PAGES_PER_STEP: i32 = 100;
+const BACKUP_PAUSE_BETWEEN_STEPS: Duration = Duration::from_millis(10);
+/// How long the backup taken on release may hold up closing the game.
+const RELEASE_BACKUP_TIME_LIMIT: Duration = Duration::from_secs(5);
+
+pub enum DbBackup {
+    Now,
+    UpdateSettings(BackupSettings),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackupSettings {
+    pub daily: bool,
+    pub on_release: bool,
+    pub path: String,
+    pub generations: i32,
+}
+
+impl BackupSettings {
+    pub fn new() -> Self {
+        Self {
+            daily: false,
+            on_release: false,
+            path: String::new(),
+            generations: 7,
+        }
+    }
+
+    /// Returns the folder backups are written to, falling back to a folder next to the database.
+    pub fn backup_dir(&self, log_path: &str) -> PathBuf {
+        if !self.path.is_empty() {
+            return PathBuf::from(&self.path);
+        }
+        let mut path = Path::new(log_path)
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        path.push(DEFAULT_BACKUP_DIR);
+        path
+    }
+}
+
+impl Default for BackupSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BackupStatus {
+    pub in_progress: bool,
+    pub last_backup: Option<chrono::DateTime<chrono::Local>>,
+    pub last_error: Option<String>,
+}
+
+impl ChatDatabase {
+    /// Requests an immediate backup on the backup thread.
+    pub fn backup_now(&self) -> Result<(), anyhow::Error> {
+        if let Some(backup_channel) = &self.backup_channel {
+            backup_channel
+                .lock()
+                .unwrap()
+                .send(DbBackup::Now)
+                .context("failed to send backup request")?;
+        }
+        Ok(())
+    }
+
+    pub fn update_backup_settings(&self, settings: &BackupSettings) -> Result<(), anyhow::Error> {
+        if let Some(backup_channel) = &self.backup_channel {
+            backup_channel
+                .lock()
+                .unwrap()
+                .send(DbBackup::UpdateSettings(settings.clone()))
+                .context("failed to send backup settings")?;
+        }
+        Ok(())
+    }
+
+    /// Copies the database at `from` into a new file at `to`, refusing to overwrite existing files.
+    pub fn copy_database(from: &str, to: &str) -> anyhow::Result<()> {
+        if Path::new(to).exists() {
+            bail!("{} already exists", to);
+        }
+        let source = Connection::open(from).context("failed to open source database")?;
+        copy_to_file(&source, Path::new(to), None)
+    }
+
+    pub(crate) fn backup_thread(
+        log_path: String,
+        pool: Pool<SqliteConnectionManager>,
+        recv_chan: mpsc::Receiver<DbBackup>,
+        mut settings: BackupSettings,
+        status: Arc<Mutex<BackupStatus>>,
+    ) -> anyhow::Result<()> {
+        let mut last_backup = latest_backup_time(&settings.backup_dir(&log_path), &log_path);
+        loop {
+            let run = match recv_chan.recv_timeout(BACKUP_CHECK_INTERVAL) {
+                Ok(DbBackup::Now) => true,
+                Ok(DbBackup::UpdateSettings(new_settings)) => {
+                    settings = new_settings;
+                    last_backup = latest_backup_time(&settings.backup_dir(&log_path), &log_path);
+                    false
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    settings.daily
+                        && last_backup.is_none_or(|last_backup| {
+                            chrono::Local::now().timestamp() - last_backup.timestamp()
+                                >= BACKUP_INTERVAL_SECONDS
+                        })
+                }
+                // sender dropped — shutdown, take the release backup if enabled and exit
+                Err(RecvTimeoutError::Disconnected) => {
+                    if settings.on_release {
+                        let deadline = Instant::now() + RELEASE_BACKUP_TIME_LIMIT;
+                        Self::run_backup(&pool, &log_path, &settings, &status, Some(deadline));
+                    }
+                    return Ok(());
+                }
+            };
+            if run {
+                if let Some(time) = Self::run_backup(&pool, &log_path, &settings, &status, None) {
+                    last_backup = Some(time);
+                }
+            }
+        }
+    }
+
+    fn run_backup(
+        pool: &Pool<SqliteConnectionManager>,
+        log_path: &str,
+        settings: &BackupSettings,
+        status: &Arc<Mutex<BackupStatus>>,
+        deadline: Option<Instant>,
+    ) -> Option<chrono::DateTime<chrono::Local>> {
+        status.lock().unwrap().in_progress = true;
+        let now = chrono::Local::now();
+        let result = Self::backup_to_dir(pool, log_path, settings, now, deadline);
+        let mut status = status.lock().unwrap();
+        status.in_progress = false;
+        match result {
+            Ok(path) => {
+                info!("backed up chat database to {}", path.display());
+                status.last_backup = Some(now);
+                status.last_error = None;
+                Some(now)
+            }
+            Err(err) => {
+                error!("failed to back up chat database: {:#}", err);
+                status.last_error = Some(format!("{:#}", err));
+                None
+            }
+        }
+    }
+
+    fn backup_to_dir(
+        pool: &Pool<SqliteConnectionManager>,
+        log_path: &str,
+        settings: &BackupSettings,
+        now: chrono::DateTime<chrono::Local>,
+        deadline: Option<Instant>,
+    ) -> anyhow::Result<PathBuf> {
+        let backup_dir = settings.backup_dir(log_path);
+        fs::create_dir_all(&backup_dir).context("failed to create backup folder")?;
+
+        let file_name = format!(
+            "{}-{}.{}",
+            backup_prefix(log_path),
+            now.format(BACKUP_TIME_FORMAT),
+            BACKUP_EXTENSION
+        );
+        let backup_path = backup_dir.join(&file_name);
+        {
+            let connection = pool.get().context("failed to get database connection")?;
+            copy_to_file(&connection, &backup_path, deadline)?;
+        }
+
+        rotate_backups(&backup_dir, log_path, settings.generations);
+        Ok(backup_path)
+    }
+}
+
+/// Copies `source` to `path` through a temporary file next to it, so an interrupted or
+/// failed copy never leaves a partial database at `path`, or counts as a backup generation.
+fn copy_to_file(source: &Connection, path: &Path, deadline: Option<Instant>) -> anyhow::Result<()> {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(format!(".{}", TMP_EXTENSION));
+    let tmp_path = PathBuf::from(tmp_path);
+    if let Err(err) = copy_into(source, &tmp_path, deadline) {
+        if let Err(remove_err) = fs::remove_file(&tmp_path) {
+            warn!("failed to remove {}: {}", tmp_path.display(), remove_err);
+        }
+        return Err(err);
+    }
+    fs::rename(&tmp_path, path).context("failed to move copy into place")
+}
+
+/// Copies the main database of `source` into `destination_path` using the online backup API,
+/// pausing between steps so writers on other connections are not blocked for long.
+/// Gives up once `deadline` has passed.
+fn copy_into(
+    source: &Connection,
+    destination_path: &Path,
+    deadline: Option<Instant>,
+) -> anyhow::Result<()> {
+    let mut destination =
+        Connection::open(destination_path).context("failed to open destination database")?;
+    let backup = Backup::new(source, &mut destination).context("failed to start backup")?;
+    while backup
+        .step(BACKUP_PAGES_PER_STEP)
+        .context("failed to copy database")?
+        != StepResult::Done
+    {
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            bail!("gave up copying the database after the time limit");
+        }
+        thread::sleep(BACKUP_PAUSE_BETWEEN_STEPS);
+    }
+    Ok(())
+}
+
+/// Backup files are named after the database file, e.g. `arcdps_chat_log-20230105-120000.db`.
+fn backup_prefix(log_path: &str) -> String {
+    Path::new(log_path)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "chat_log".to_owned())
+}
+
+fn list_backups(backup_dir: &Path, log_path: &str) -> Vec<String> {
+    list_files(backup_dir, log_path, &format!(".{}", BACKUP_EXTENSION))
+}
+
+fn list_files(backup_dir: &Path, log_path: &str, suffix: &str) -> Vec<String> {
+    let prefix = format!("{}-", backup_prefix(log_path));
+    let Ok(entries) = fs::read_dir(backup_dir) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| name.starts_with(&prefix) && name.ends_with(suffix))
+        .collect();
+    names.sort();
+    names
+}
+
+fn latest_backup_time(
+    backup_dir: &Path,
+    log_path: &str,
+) -> Option<chrono::DateTime<chrono::Local>> {
+    let latest = list_backups(backup_dir, log_path).pop()?;
+    let modified = fs::metadata(backup_dir.join(latest))
+        .and_then(|metadata| metadata.modified())
+        .ok()?;
+    Some(modified.into())
+}
+
+fn rotate_backups(backup_dir: &Path, log_path: &str, generations: i32) {
+    let backups = list_backups(backup_dir, log_path);
+    for name in backups_to_remove(&backups, generations) {
+        let path = backup_dir.join(name);
+        if let Err(err) = fs::remove_file(&path) {
+            warn!("failed to remove old backup {}: {}", path.display(), err);
+        }
+    }
+    // left behind when the game closed in the middle of a backup
+    let tmp_suffix = format!(".{}.{}", BACKUP_EXTENSION, TMP_EXTENSION);
+    for name in list_files(backup_dir, log_path, &tmp_suffix) {
+        let path = backup_dir.join(name);
+        if let Err(err) = fs::remove_file(&path) {
+            warn!(
+                "failed to remove unfinished backup {}: {}",
+                path.display(),
+                err
+            );
+        }
+    }
+}
+
+/// Returns the oldest backups beyond the number of generations to keep.
+/// `backups` must be sorted oldest first.
+fn backups_to_remove(backups: &[String], generations: i32) -> &[String] {
+    let keep = generations.max(1) as usize;
+    &backups[..backups.len().saturating_sub(keep)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backups_to_remove() {
+        let backups: Vec<String> = [
+            "arcdps_chat_log-20230101-120000.db",
+            "arcdps_chat_log-20230102-120000.db",
+            "arcdps_chat_log-20230103-120000.db",
+        ]
+        .iter()
+        .map(|x| x.to_string())
+        .collect();
+        assert_eq!(backups_to_remove(&backups, 2), &backups[..1]);
+        assert_eq!(backups_to_remove(&backups, 3), &[] as &[String]);
+        assert_eq!(backups_to_remove(&backups, 5), &[] as &[String]);
+        assert_eq!(backups_to_remove(&backups, 0), &backups[..2]);
+    }
+
+    #[test]
+    fn test_copy_to_file() {
+        let source = Connection::open_in_memory().unwrap();
+        source
+            .execute_batch(
+                "CREATE TABLE filler (data BLOB); INSERT INTO filler VALUES (zeroblob(1000000));",
+            )
+            .unwrap();
+        let dir = std::env::temp_dir().join(format!("chat_log_copy_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("copy.db");
+
+        // out of time after the first step, the partial copy is removed
+        assert!(copy_to_file(&source, &path, Some(Instant::now())).is_err());
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+
+        copy_to_file(&source, &path, None).unwrap();
+        let copy = Connection::open(&path).unwrap();
+        let size: i64 = copy
+            .query_row("SELECT length(data) FROM filler", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(size, 1000000);
+        drop(copy);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_backup_dir() {
+        let mut settings = BackupSettings::new();
+        assert_eq!(
+            settings.backup_dir("addons/arcdps/arcdps_chat_log.db"),
+            PathBuf::from("addons/arcdps").join(DEFAULT_BACKUP_DIR)
+        );
+        settings.path = "D:/backups".to_owned();
+        assert_eq!(
+            settings.backup_dir("addons/arcdps/arcdps_chat_log.db"),
+            PathBuf::from("D:/backups")
+        );
+    }
+}
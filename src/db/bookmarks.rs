@@ -0,0 +1,246 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    sync::{Arc, Mutex},
+};
+
+use anyhow::Context;
+use arcdps::extras::message::SquadMessageOwned;
+use chrono::{DateTime, FixedOffset, TimeZone};
+use log::error;
+use rusqlite::{params, Connection};
+
+use super::{
+    insert::DbInsert,
+    query::{DbQuery, Queried, StoredMessage, MESSAGE_COLUMNS},
+    ChatDatabase,
+};
+
+/// The message a bookmark refers to.
+#[derive(Clone)]
+pub enum BookmarkTarget {
+    /// A message row returned from the database.
+    Id(i64),
+    /// A message received this session, looked up by its contents once it has been inserted.
+    Message(MessageKey),
+}
+
+/// Identifies a message received this session, without keeping a copy of its text.
+#[derive(Clone, Debug)]
+pub struct MessageKey {
+    timestamp: Option<DateTime<FixedOffset>>,
+    pub account_name: String,
+    text_hash: u64,
+}
+
+impl MessageKey {
+    pub fn new(message: &SquadMessageOwned) -> Self {
+        Self {
+            timestamp: message.timestamp,
+            account_name: message.account_name.clone(),
+            text_hash: text_hash(&message.text),
+        }
+    }
+}
+
+fn text_hash(text: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    text.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[derive(Clone)]
+pub struct BookmarkToAdd {
+    pub(crate) target: BookmarkTarget,
+    pub(crate) annotation: String,
+    cur_time: i64,
+}
+
+impl BookmarkToAdd {
+    pub fn new(target: BookmarkTarget, annotation: &str) -> Self {
+        Self {
+            target,
+            annotation: annotation.to_owned(),
+            cur_time: chrono::Utc::now().timestamp(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Bookmark {
+    pub message: StoredMessage,
+    pub annotation: String,
+    pub created: i64,
+}
+
+impl Bookmark {
+    pub fn created(&self) -> chrono::DateTime<chrono::Local> {
+        chrono::Utc
+            .timestamp_opt(self.created, 0)
+            .unwrap()
+            .with_timezone(&chrono::Local)
+    }
+}
+
+impl ChatDatabase {
+    pub fn add_bookmark(&self, bookmark: BookmarkToAdd) -> Result<(), anyhow::Error> {
+        self.send_insert(DbInsert::AddBookmark(bookmark))
+            .context("failed to insert bookmark into insert channel")
+    }
+
+    pub fn update_bookmark(&self, message_id: i64, annotation: &str) -> Result<(), anyhow::Error> {
+        self.send_insert(DbInsert::UpdateBookmark(message_id, annotation.to_owned()))
+            .context("failed to insert bookmark update into insert channel")
+    }
+
+    pub fn delete_bookmark(&self, message_id: i64) -> Result<(), anyhow::Error> {
+        self.send_insert(DbInsert::DeleteBookmark(message_id))
+            .context("failed to insert bookmark deletion into insert channel")
+    }
+
+    fn send_insert(&self, insert: DbInsert) -> Result<(), anyhow::Error> {
+        if let Some(insert_channel) = &self.insert_channel {
+            insert_channel.lock().unwrap().send(insert)?;
+        }
+        Ok(())
+    }
+
+    /// Lists bookmarks on the query thread, newest message first. `result` is set to
+    /// `Queried::Pending` until the query completes.
+    pub fn query_bookmarks(&self, result: &Arc<Mutex<Queried<Vec<Bookmark>>>>) {
+        *result.lock().unwrap() = Queried::Pending;
+        if let Some(query_channel) = &self.query_channel {
+            if let Err(err) = query_channel
+                .lock()
+                .unwrap()
+                .send(DbQuery::Bookmarks(result.clone()))
+            {
+                error!("failed to send query: {:#}", err);
+                *result.lock().unwrap() = Queried::Error;
+            }
+        }
+    }
+
+    /// Resolves the message row for `target`. Messages from this session are matched on
+    /// their contents, preferring the most recent match.
+    pub(crate) fn bookmark_message_id(
+        connection: &Connection,
+        target: &BookmarkTarget,
+    ) -> rusqlite::Result<Option<i64>> {
+        match target {
+            BookmarkTarget::Id(id) => Ok(Some(*id)),
+            BookmarkTarget::Message(key) => {
+                let mut statement = connection.prepare_cached(
+                    "SELECT rowid, text FROM messages
+                    WHERE timestamp=?1 AND account_name=?2
+                    ORDER BY rowid DESC",
+                )?;
+                let mut rows = statement.query(params![key.timestamp, key.account_name])?;
+                while let Some(row) = rows.next()? {
+                    let text: String = row.get(1)?;
+                    if text_hash(&text) == key.text_hash {
+                        return row.get(0).map(Some);
+                    }
+                }
+                Ok(None)
+            }
+        }
+    }
+
+    pub(crate) fn insert_bookmark(
+        connection: &Connection,
+        bookmark: &BookmarkToAdd,
+    ) -> anyhow::Result<()> {
+        let message_id = Self::bookmark_message_id(connection, &bookmark.target)
+            .context("failed to look up bookmarked message")?
+            .context("bookmarked message is not in the database")?;
+        connection
+            .prepare_cached(
+                "INSERT INTO bookmarks (message_id, annotation, created) VALUES (?1, ?2, ?3)
+                ON CONFLICT (message_id) DO UPDATE SET annotation=?2",
+            )
+            .context("failed to prepare bookmark insert statement")?
+            .execute(params![message_id, bookmark.annotation, bookmark.cur_time])
+            .context("failed to insert bookmark")?;
+        Ok(())
+    }
+
+    pub(crate) fn query_bookmark_list(connection: &Connection) -> rusqlite::Result<Vec<Bookmark>> {
+        let mut statement = connection.prepare_cached(&format!(
+            "SELECT {}, bookmarks.annotation, bookmarks.created FROM bookmarks
+            JOIN messages ON messages.rowid = bookmarks.message_id
+            ORDER BY messages.rowid DESC",
+            MESSAGE_COLUMNS
+        ))?;
+        let bookmarks = statement
+            .query_map([], |row| {
+                Ok(Bookmark {
+                    message: StoredMessage::from_row(row)?,
+                    annotation: row.get(9)?,
+                    created: row.get(10)?,
+                })
+            })?
+            .collect();
+        bookmarks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_connection;
+    use arcdps::extras::message::{ChannelType, SquadMessageFlags};
+
+    #[test]
+    fn test_bookmarks() {
+        let connection = test_connection();
+        let timestamp = chrono::DateTime::parse_from_rfc3339("2023-01-05T12:00:00+00:00").ok();
+        let message = SquadMessageOwned {
+            channel_id: 0,
+            channel_type: ChannelType::Squad,
+            subgroup: 255,
+            flags: SquadMessageFlags::empty(),
+            timestamp,
+            account_name: "Foo.1234".to_owned(),
+            character_name: "Foo".to_owned(),
+            text: "stack on tag".to_owned(),
+        };
+        connection
+            .execute(
+                "INSERT INTO messages VALUES (0, 'Squad', 255, 0, ?1, 'Foo.1234', 'Foo', 'stack on tag', 1)",
+                params![timestamp],
+            )
+            .unwrap();
+
+        ChatDatabase::insert_bookmark(
+            &connection,
+            &BookmarkToAdd::new(
+                BookmarkTarget::Message(MessageKey::new(&message)),
+                "pull timer",
+            ),
+        )
+        .unwrap();
+        let bookmarks = ChatDatabase::query_bookmark_list(&connection).unwrap();
+        assert_eq!(bookmarks.len(), 1);
+        assert_eq!(bookmarks[0].message.text, "stack on tag");
+        assert_eq!(bookmarks[0].annotation, "pull timer");
+
+        // bookmarking again keeps a single bookmark
+        ChatDatabase::insert_bookmark(
+            &connection,
+            &BookmarkToAdd::new(BookmarkTarget::Id(bookmarks[0].message.id), "updated"),
+        )
+        .unwrap();
+        let bookmarks = ChatDatabase::query_bookmark_list(&connection).unwrap();
+        assert_eq!(bookmarks.len(), 1);
+        assert_eq!(bookmarks[0].annotation, "updated");
+
+        let mut missing = message;
+        missing.text = "not logged".to_owned();
+        assert!(ChatDatabase::insert_bookmark(
+            &connection,
+            &BookmarkToAdd::new(BookmarkTarget::Message(MessageKey::new(&missing)), "")
+        )
+        .is_err());
+    }
+}
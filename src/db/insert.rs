@@ -2,11 +2,13 @@ use std::sync::mpsc;
 
 use anyhow::Context;
 use arcdps::extras::message::{SquadMessageFlags, SquadMessageOwned};
+use log::{error, info};
 use r2d2::Pool;
 use r2d2_sqlite::SqliteConnectionManager;
-use rusqlite::{params, types::Null};
+use rusqlite::{params, types::Null, Connection};
 
 use super::{
+    bookmarks::BookmarkToAdd,
     query::{Note, QueriedNote},
     ChatDatabase,
 };
@@ -16,6 +18,10 @@ pub enum DbInsert {
     AddNote(NoteToAdd),
     DeleteNote(String),
     ColorNote(NoteColorUpdate),
+    AddBookmark(BookmarkToAdd),
+    UpdateBookmark(i64, String),
+    DeleteBookmark(i64),
+    PurgeAccount(String),
 }
 
 #[derive(Clone)]
@@ -137,6 +143,39 @@ impl ChatDatabase {
         Ok(())
     }
 
+    /// Deletes every message, bookmark and note stored for `account_name`.
+    pub fn purge_account(&self, account_name: &str) -> Result<(), anyhow::Error> {
+        if let Some(insert_channel) = &self.insert_channel {
+            insert_channel
+                .lock()
+                .unwrap()
+                .send(DbInsert::PurgeAccount(account_name.to_owned()))
+                .context("failed to insert account purge into insert channel")?;
+            self.note_cache
+                .lock()
+                .unwrap()
+                .insert(account_name.to_owned(), QueriedNote::NotFound);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn purge_account_rows(
+        connection: &Connection,
+        account_name: &str,
+    ) -> rusqlite::Result<usize> {
+        let transaction = connection.unchecked_transaction()?;
+        let messages = transaction.execute(
+            "DELETE FROM messages WHERE account_name=?1",
+            params![account_name],
+        )?;
+        transaction.execute(
+            "DELETE FROM notes WHERE account_name=?1",
+            params![account_name],
+        )?;
+        transaction.commit()?;
+        Ok(messages)
+    }
+
     pub(crate) fn insert_thread(
         game_start: i64,
         pool: Pool<SqliteConnectionManager>,
@@ -224,7 +263,81 @@ impl ChatDatabase {
                         .execute(params![account_name,])
                         .context("failed to delete note")?;
                 }
+                DbInsert::AddBookmark(bookmark) => {
+                    // the message may not have been logged, so don't take down the thread
+                    if let Err(err) = Self::insert_bookmark(&connection, &bookmark) {
+                        error!("failed to add bookmark: {:#}", err);
+                    }
+                }
+                DbInsert::UpdateBookmark(message_id, annotation) => {
+                    let mut statement = connection
+                        .prepare_cached("UPDATE bookmarks SET annotation=?1 WHERE message_id=?2")
+                        .context("failed to prepare bookmark update statement")?;
+                    statement
+                        .execute(params![annotation, message_id])
+                        .context("failed to update bookmark")?;
+                }
+                DbInsert::DeleteBookmark(message_id) => {
+                    let mut statement = connection
+                        .prepare_cached("DELETE FROM bookmarks WHERE message_id=?1")
+                        .context("failed to prepare delete bookmark statement")?;
+                    statement
+                        .execute(params![message_id])
+                        .context("failed to delete bookmark")?;
+                }
+                DbInsert::PurgeAccount(account_name) => {
+                    let messages = Self::purge_account_rows(&connection, &account_name)
+                        .context("failed to purge account")?;
+                    info!("purged {} messages from {}", messages, account_name);
+                }
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_connection;
+
+    #[test]
+    fn test_purge_account_rows() {
+        let connection = test_connection();
+        connection
+            .execute_batch(
+                "INSERT INTO messages VALUES (0, 'Squad', 255, 0, '2023-01-05 12:00:00+00:00', 'Foo.1234', 'Foo', 'stack', 1);
+                INSERT INTO messages VALUES (0, 'Squad', 255, 0, '2023-01-05 12:01:00+00:00', 'Bar.5678', 'Bar', 'ready', 1);
+                INSERT INTO notes VALUES ('Foo.1234', 'commander', 1, 1, NULL, NULL, NULL);
+                INSERT INTO notes VALUES ('Bar.5678', 'healer', 1, 1, NULL, NULL, NULL);
+                INSERT INTO bookmarks VALUES (1, '', 1);
+                INSERT INTO bookmarks VALUES (2, '', 1);",
+            )
+            .unwrap();
+
+        assert_eq!(
+            ChatDatabase::purge_account_rows(&connection, "Foo.1234").unwrap(),
+            1
+        );
+        let count = |table: &str, account_name: &str| -> i64 {
+            connection
+                .query_row(
+                    &format!("SELECT COUNT(*) FROM {} WHERE account_name=?1", table),
+                    params![account_name],
+                    |row| row.get(0),
+                )
+                .unwrap()
+        };
+        assert_eq!(count("messages", "Foo.1234"), 0);
+        assert_eq!(count("notes", "Foo.1234"), 0);
+        assert_eq!(count("messages", "Bar.5678"), 1);
+        assert_eq!(count("notes", "Bar.5678"), 1);
+        let bookmarks: Vec<i64> = connection
+            .prepare("SELECT message_id FROM bookmarks")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(bookmarks, vec![2]);
+    }
+}
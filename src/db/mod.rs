@@ -1,22 +1,28 @@
+pub mod backup;
+pub mod bookmarks;
 pub mod insert;
 pub mod query;
+pub mod stats;
 
 use std::{
     collections::HashMap,
+    path::Path,
     sync::{
         mpsc::{self, Sender},
         Arc, Mutex,
     },
-    thread::Builder,
+    thread::{Builder, JoinHandle},
 };
 
-use anyhow::Context;
+use anyhow::{bail, Context};
 use log::error;
 use r2d2::Pool;
 use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::OpenFlags;
 use rusqlite_migration::{Migrations, M};
 
 use self::{
+    backup::{BackupSettings, BackupStatus, DbBackup},
     insert::DbInsert,
     query::{DbQuery, QueriedNote},
 };
@@ -26,23 +32,74 @@ pub struct ChatDatabase {
     pub connection_pool: Option<Pool<SqliteConnectionManager>>,
     pub insert_channel: Option<Mutex<Sender<DbInsert>>>,
     pub query_channel: Option<Mutex<Sender<DbQuery>>>,
+    pub backup_channel: Option<Mutex<Sender<DbBackup>>>,
     pub note_cache: Arc<Mutex<HashMap<String, QueriedNote>>>,
+    pub backup_status: Arc<Mutex<BackupStatus>>,
+    insert_thread: Option<JoinHandle<()>>,
+    backup_thread: Option<JoinHandle<()>>,
+    pub read_only: bool,
+}
+
+fn migrations() -> Migrations<'static> {
+    Migrations::new(vec![
+        M::up(include_str!(
+            "../../migrations/2022-08-07-create-messages.sql"
+        )),
+        M::up(include_str!(
+            "../../migrations/2022-08-07-messages-timestamp-index.sql"
+        )),
+        M::up(include_str!("../../migrations/2023-01-05-create-notes.sql")),
+        M::up(include_str!(
+            "../../migrations/2023-01-12-add-notes-color.sql"
+        )),
+        M::up(include_str!(
+            "../../migrations/2026-10-19-create-bookmarks.sql"
+        )),
+    ])
+}
+
+/// Converts unix seconds to a value that compares with the stored `timestamp` column, so
+/// time bounds can be checked against the column directly and use its index.
+pub(crate) fn stored_timestamp(unix: i64) -> Option<chrono::DateTime<chrono::Utc>> {
+    chrono::DateTime::from_timestamp(unix, 0)
+}
+
+/// An in-memory database with every migration applied.
+#[cfg(test)]
+pub(crate) fn test_connection() -> rusqlite::Connection {
+    let mut connection = rusqlite::Connection::open_in_memory().unwrap();
+    migrations().to_latest(&mut connection).unwrap();
+    connection
+}
+
+/// A `file:` URI that opens the database at `path` without ever writing to it. `immutable`
+/// also keeps SQLite away from the `-wal` and `-shm` files of a WAL database, so changes
+/// that were never checkpointed into the main file are not seen.
+fn read_only_uri(path: &str) -> String {
+    let mut path = path
+        .replace('%', "%25")
+        .replace('?', "%3f")
+        .replace('#', "%23")
+        .replace('\\', "/");
+    // a drive letter is part of the path, not the authority
+    if path.as_bytes().get(1) == Some(&b':') {
+        path.insert(0, '/');
+    }
+    let separator = if path.starts_with('/') { "//" } else { "" };
+    format!("file:{}{}?mode=ro&immutable=1", separator, path)
+}
+
+fn read_only_flags() -> OpenFlags {
+    OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_URI | OpenFlags::SQLITE_OPEN_NO_MUTEX
 }
 
 impl ChatDatabase {
-    pub fn try_new(log_path: &str, game_start: i64) -> anyhow::Result<Self> {
-        let migrations = Migrations::new(vec![
-            M::up(include_str!(
-                "../../migrations/2022-08-07-create-messages.sql"
-            )),
-            M::up(include_str!(
-                "../../migrations/2022-08-07-messages-timestamp-index.sql"
-            )),
-            M::up(include_str!("../../migrations/2023-01-05-create-notes.sql")),
-            M::up(include_str!(
-                "../../migrations/2023-01-12-add-notes-color.sql"
-            )),
-        ]);
+    pub fn try_new(
+        log_path: &str,
+        game_start: i64,
+        backup_settings: &BackupSettings,
+    ) -> anyhow::Result<Self> {
+        let migrations = migrations();
 
         let manager = SqliteConnectionManager::file(log_path);
         let pool = Pool::new(manager).context("failed to create pool")?;
@@ -58,7 +115,7 @@ impl ChatDatabase {
 
         let (insert_send, insert_recv) = mpsc::channel::<DbInsert>();
         let clone_pool = pool.clone();
-        let _insert_thread = Builder::new()
+        let insert_thread = Builder::new()
             .name("chat_insert".to_owned())
             .spawn(
                 move || match Self::insert_thread(game_start, clone_pool, insert_recv) {
@@ -67,40 +124,175 @@ impl ChatDatabase {
                         error!("insert thread failed: {:#}", err);
                     }
                 },
-            );
+            )
+            .context("failed to spawn insert thread")?;
 
-        let (query_send, query_recv) = mpsc::channel::<DbQuery>();
-        let clone_pool = pool.clone();
         let note_cache = Arc::new(Mutex::new(HashMap::new()));
-        let clone_note_cache = note_cache.clone();
-        let _query_thread = Builder::new().name("chat_query".to_owned()).spawn(move || {
-            match Self::query_thread(clone_pool, query_recv, clone_note_cache) {
-                Ok(_) => {}
-                Err(err) => {
-                    error!("query thread failed: {:#}", err);
+        let query_send = Self::spawn_query_thread(&pool, &note_cache)?;
+
+        let (backup_send, backup_recv) = mpsc::channel::<DbBackup>();
+        let clone_pool = pool.clone();
+        let backup_status = Arc::new(Mutex::new(BackupStatus::default()));
+        let clone_backup_status = backup_status.clone();
+        let clone_log_path = log_path.to_owned();
+        let clone_backup_settings = backup_settings.clone();
+        let backup_thread = Builder::new()
+            .name("chat_backup".to_owned())
+            .spawn(move || {
+                match Self::backup_thread(
+                    clone_log_path,
+                    clone_pool,
+                    backup_recv,
+                    clone_backup_settings,
+                    clone_backup_status,
+                ) {
+                    Ok(_) => {}
+                    Err(err) => {
+                        error!("backup thread failed: {:#}", err);
+                    }
                 }
-            }
-        });
+            })
+            .context("failed to spawn backup thread")?;
 
         Ok(Self {
             log_path: log_path.to_string(),
             connection_pool: Some(pool),
             insert_channel: Some(Mutex::new(insert_send)),
             query_channel: Some(Mutex::new(query_send)),
+            backup_channel: Some(Mutex::new(backup_send)),
             note_cache,
+            backup_status,
+            insert_thread: Some(insert_thread),
+            backup_thread: Some(backup_thread),
+            read_only: false,
             // game_start,
         })
     }
 
+    /// Opens an existing database without applying migrations or starting the insert and
+    /// backup threads, so the file is never modified.
+    pub fn try_open_read_only(log_path: &str) -> anyhow::Result<Self> {
+        if !Path::new(log_path).is_file() {
+            bail!("{} does not exist", log_path);
+        }
+        let manager =
+            SqliteConnectionManager::file(read_only_uri(log_path)).with_flags(read_only_flags());
+        let pool = Pool::new(manager).context("failed to create pool")?;
+        {
+            let connection = pool.get().context("failed to get database connection")?;
+            let has_messages: bool = connection
+                .query_row(
+                    "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type='table' AND name='messages')",
+                    [],
+                    |row| row.get(0),
+                )
+                .context("failed to read database schema")?;
+            if !has_messages {
+                bail!("{} is not a chat log database", log_path);
+            }
+        }
+
+        let note_cache = Arc::new(Mutex::new(HashMap::new()));
+        let query_send = Self::spawn_query_thread(&pool, &note_cache)?;
+
+        Ok(Self {
+            log_path: log_path.to_string(),
+            connection_pool: Some(pool),
+            insert_channel: None,
+            query_channel: Some(Mutex::new(query_send)),
+            backup_channel: None,
+            note_cache,
+            backup_status: Arc::new(Mutex::new(BackupStatus::default())),
+            insert_thread: None,
+            backup_thread: None,
+            read_only: true,
+        })
+    }
+
+    fn spawn_query_thread(
+        pool: &Pool<SqliteConnectionManager>,
+        note_cache: &Arc<Mutex<HashMap<String, QueriedNote>>>,
+    ) -> anyhow::Result<Sender<DbQuery>> {
+        let (query_send, query_recv) = mpsc::channel::<DbQuery>();
+        let clone_pool = pool.clone();
+        let clone_note_cache = note_cache.clone();
+        Builder::new()
+            .name("chat_query".to_owned())
+            .spawn(
+                move || match Self::query_thread(clone_pool, query_recv, clone_note_cache) {
+                    Ok(_) => {}
+                    Err(err) => {
+                        error!("query thread failed: {:#}", err);
+                    }
+                },
+            )
+            .context("failed to spawn query thread")?;
+        Ok(query_send)
+    }
+
     pub fn release(&mut self) {
         {
             // take channel out to drop it out of scope
             // this should cause the recv channel to close and the pool
             let _ = self.insert_channel.take();
             let _ = self.query_channel.take();
+            // wait for pending inserts to be written before the release backup is taken
+            if let Some(insert_thread) = self.insert_thread.take() {
+                if insert_thread.join().is_err() {
+                    error!("failed to join insert thread");
+                }
+            }
+            let _ = self.backup_channel.take();
+            if let Some(backup_thread) = self.backup_thread.take() {
+                if backup_thread.join().is_err() {
+                    error!("failed to join backup thread");
+                }
+            }
             // take pool out to drop it out of scope
             // this should close all connections
             let _ = self.connection_pool.take();
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_only_uri() {
+        assert_eq!(
+            read_only_uri("addons/arcdps/arcdps_chat_log.db"),
+            "file:addons/arcdps/arcdps_chat_log.db?mode=ro&immutable=1"
+        );
+        assert_eq!(
+            read_only_uri(r"C:\Guild Wars 2\chat #1?.db"),
+            "file:///C:/Guild Wars 2/chat %231%3f.db?mode=ro&immutable=1"
+        );
+
+        let dir =
+            std::env::temp_dir().join(format!("chat_log_read_only_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("chat.db");
+        {
+            let mut connection = rusqlite::Connection::open(&path).unwrap();
+            migrations().to_latest(&mut connection).unwrap();
+            connection
+                .pragma_update(None, "journal_mode", "WAL")
+                .unwrap();
+        }
+        let connection = rusqlite::Connection::open_with_flags(
+            read_only_uri(path.to_str().unwrap()),
+            read_only_flags(),
+        )
+        .unwrap();
+        let count: i64 = connection
+            .query_row("SELECT COUNT(*) FROM messages", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 0);
+        drop(connection);
+        // no -wal or -shm files were created next to it
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -4,18 +4,132 @@ use std::{
 };
 
 use anyhow::Context;
+use arcdps::extras::message::{ChannelType, SquadMessageFlags, SquadMessageOwned};
 use chrono::TimeZone;
 use log::error;
 use r2d2::Pool;
 use r2d2_sqlite::SqliteConnectionManager;
-use rusqlite::params;
+use rusqlite::{params, Connection, Row};
 
-use super::ChatDatabase;
+use super::{
+    bookmarks::Bookmark,
+    stats::{ChatStats, StatsQuery},
+    stored_timestamp, ChatDatabase,
+};
 
 pub enum DbQuery {
     Note(String),
+    Messages(MessageSearch, Arc<Mutex<Queried<Vec<StoredMessage>>>>),
+    Stats(StatsQuery, Arc<Mutex<Queried<ChatStats>>>),
+    Bookmarks(Arc<Mutex<Queried<Vec<Bookmark>>>>),
+}
+
+/// Result of a query that is answered asynchronously by the query thread.
+#[derive(Clone)]
+pub enum Queried<T> {
+    Success(T),
+    Error,
+    Pending,
+}
+
+#[derive(Clone, Default)]
+pub struct MessageSearch {
+    pub text: String,
+    /// Matches either the account or character name.
+    pub user: String,
+    pub account: String,
+    pub character: String,
+    /// Channel type as stored, e.g. `Squad`.
+    pub channel: Option<String>,
+    /// Subgroup as stored, starting from 0.
+    pub subgroup: Option<u8>,
+    /// Unix timestamps bounding when the message was sent.
+    pub after: Option<i64>,
+    pub before: Option<i64>,
+    pub limit: usize,
+}
+
+impl MessageSearch {
+    pub fn new(text: &str, user: &str, limit: usize) -> Self {
+        Self {
+            text: text.to_owned(),
+            user: user.to_owned(),
+            limit,
+            ..Default::default()
+        }
+    }
 }
 
+#[derive(Clone)]
+pub struct StoredMessage {
+    pub id: i64,
+    pub channel_type: String,
+    pub subgroup: u8,
+    pub is_broadcast: bool,
+    pub timestamp: Option<chrono::DateTime<chrono::FixedOffset>>,
+    pub account_name: String,
+    pub character_name: String,
+    pub text: String,
+    pub game_start: i64,
+}
+
+impl StoredMessage {
+    pub(crate) fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: row.get(0)?,
+            channel_type: row.get(1)?,
+            subgroup: row.get(2)?,
+            is_broadcast: row.get(3)?,
+            timestamp: row.get(4)?,
+            account_name: row.get(5)?,
+            character_name: row.get(6)?,
+            text: row.get(7)?,
+            game_start: row.get(8)?,
+        })
+    }
+
+    pub fn timestamp(&self) -> Option<chrono::DateTime<chrono::Local>> {
+        self.timestamp
+            .map(|timestamp| timestamp.with_timezone(&chrono::Local))
+    }
+
+    /// Rebuilds the chat message as received from extras. The channel id isn't kept.
+    pub fn to_squad_message(&self) -> SquadMessageOwned {
+        let mut flags = SquadMessageFlags::empty();
+        flags.set(SquadMessageFlags::IS_BROADCAST, self.is_broadcast);
+        SquadMessageOwned {
+            channel_id: 0,
+            channel_type: match self.channel_type.as_str() {
+                "Squad" => ChannelType::Squad,
+                "Party" => ChannelType::Party,
+                _ => ChannelType::Invalid,
+            },
+            subgroup: self.subgroup,
+            flags,
+            timestamp: self.timestamp,
+            account_name: self.account_name.clone(),
+            character_name: self.character_name.clone(),
+            text: self.text.clone(),
+        }
+    }
+
+    /// Returns the channel prefix as shown in the squad log, e.g. `[Squad][2]`.
+    pub fn channel_label(&self) -> String {
+        let mut label = format!("[{}]", self.channel_type);
+        if self.channel_type == "Squad" {
+            if self.subgroup != 255 {
+                label.push_str(&format!("[{}]", self.subgroup as u16 + 1));
+            }
+            if self.is_broadcast {
+                label.push_str("[BROADCAST]");
+            }
+        }
+        label
+    }
+}
+
+pub(crate) const MESSAGE_COLUMNS: &str = "messages.rowid, channel_type, subgroup, is_broadcast, timestamp, account_name, character_name, text, game_start";
+
 #[derive(Clone)]
 pub struct Note {
     pub account_name: String,
@@ -78,6 +192,92 @@ impl ChatDatabase {
         QueriedNote::Pending
     }
 
+    /// Searches messages on the query thread, newest first. `result` is set to
+    /// `Queried::Pending` until the query completes.
+    pub fn search_messages(
+        &self,
+        search: MessageSearch,
+        result: &Arc<Mutex<Queried<Vec<StoredMessage>>>>,
+    ) {
+        *result.lock().unwrap() = Queried::Pending;
+        if let Some(query_channel) = &self.query_channel {
+            if let Err(err) = query_channel
+                .lock()
+                .unwrap()
+                .send(DbQuery::Messages(search, result.clone()))
+            {
+                error!("failed to send query: {:#}", err);
+                *result.lock().unwrap() = Queried::Error;
+            }
+        }
+    }
+
+    /// Returns the latest `limit` messages, newest first. Runs on the calling thread.
+    pub fn recent_messages(&self, limit: usize) -> anyhow::Result<Vec<StoredMessage>> {
+        let Some(pool) = &self.connection_pool else {
+            return Ok(Vec::new());
+        };
+        let connection = pool.get().context("failed to get database connection")?;
+        Self::query_messages(&connection, &MessageSearch::new("", "", limit))
+            .context("failed to query recent messages")
+    }
+
+    /// Returns every account with a note. Runs on the calling thread.
+    pub fn noted_accounts(&self) -> anyhow::Result<Vec<String>> {
+        let Some(pool) = &self.connection_pool else {
+            return Ok(Vec::new());
+        };
+        let connection = pool.get().context("failed to get database connection")?;
+        let mut statement = connection
+            .prepare_cached("SELECT account_name FROM notes")
+            .context("failed to prepare statement")?;
+        let accounts = statement
+            .query_map([], |row| row.get(0))
+            .and_then(|rows| rows.collect())
+            .context("failed to query noted accounts");
+        accounts
+    }
+
+    pub(crate) fn query_messages(
+        connection: &Connection,
+        search: &MessageSearch,
+    ) -> rusqlite::Result<Vec<StoredMessage>> {
+        let mut statement = connection.prepare_cached(&format!(
+            "SELECT {} FROM messages
+            WHERE (?1 = '' OR text LIKE ?2 ESCAPE '\\')
+            AND (?3 = '' OR account_name LIKE ?4 ESCAPE '\\' OR character_name LIKE ?4 ESCAPE '\\')
+            AND (?6 = '' OR account_name LIKE ?7 ESCAPE '\\')
+            AND (?8 = '' OR character_name LIKE ?9 ESCAPE '\\')
+            AND (?10 IS NULL OR channel_type = ?10)
+            AND (?11 IS NULL OR subgroup = ?11)
+            AND (?12 IS NULL OR timestamp >= ?12)
+            AND (?13 IS NULL OR timestamp <= ?13)
+            ORDER BY rowid DESC LIMIT ?5",
+            MESSAGE_COLUMNS
+        ))?;
+        let messages = statement
+            .query_map(
+                params![
+                    search.text,
+                    like_pattern(&search.text),
+                    search.user,
+                    like_pattern(&search.user),
+                    search.limit as i64,
+                    search.account,
+                    like_pattern(&search.account),
+                    search.character,
+                    like_pattern(&search.character),
+                    search.channel,
+                    search.subgroup,
+                    search.after.and_then(stored_timestamp),
+                    search.before.and_then(stored_timestamp),
+                ],
+                StoredMessage::from_row,
+            )?
+            .collect();
+        messages
+    }
+
     pub(crate) fn query_thread(
         pool: Pool<SqliteConnectionManager>,
         recv_chan: mpsc::Receiver<DbQuery>,
@@ -145,7 +345,111 @@ impl ChatDatabase {
                             .insert(account_name.to_owned(), QueriedNote::NotFound);
                     }
                 }
+                DbQuery::Messages(search, result) => {
+                    let messages = match Self::query_messages(&connection, &search) {
+                        Ok(messages) => Queried::Success(messages),
+                        Err(err) => {
+                            error!("failed to query messages: {:#}", err);
+                            Queried::Error
+                        }
+                    };
+                    *result.lock().unwrap() = messages;
+                }
+                DbQuery::Stats(query, result) => {
+                    let stats = match Self::compute_stats(&connection, &query) {
+                        Ok(stats) => Queried::Success(stats),
+                        Err(err) => {
+                            error!("failed to query stats: {:#}", err);
+                            Queried::Error
+                        }
+                    };
+                    *result.lock().unwrap() = stats;
+                }
+                DbQuery::Bookmarks(result) => {
+                    let bookmarks = match Self::query_bookmark_list(&connection) {
+                        Ok(bookmarks) => Queried::Success(bookmarks),
+                        Err(err) => {
+                            error!("failed to query bookmarks: {:#}", err);
+                            Queried::Error
+                        }
+                    };
+                    *result.lock().unwrap() = bookmarks;
+                }
             }
         }
     }
 }
+
+/// Escapes `text` for use as a substring match in a `LIKE ... ESCAPE '\'` expression.
+fn like_pattern(text: &str) -> String {
+    let mut pattern = String::with_capacity(text.len() + 2);
+    pattern.push('%');
+    for c in text.chars() {
+        if matches!(c, '\\' | '%' | '_') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern.push('%');
+    pattern
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_connection;
+
+    fn connection_with_messages() -> Connection {
+        let connection = test_connection();
+        connection
+            .execute_batch(
+                "INSERT INTO messages VALUES (0, 'Squad', 255, 0, '2023-01-05 12:00:00+00:00', 'Foo.1234', 'Foo', 'stack on tag', 1);
+                INSERT INTO messages VALUES (0, 'Party', 0, 0, '2023-01-05 12:01:00+00:00', 'Bar.5678', 'Bar', '100% ready', 1);",
+            )
+            .unwrap();
+        connection
+    }
+
+    #[test]
+    fn test_query_messages() {
+        let connection = connection_with_messages();
+        let messages =
+            ChatDatabase::query_messages(&connection, &MessageSearch::new("", "", 10)).unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].character_name, "Bar");
+
+        let messages =
+            ChatDatabase::query_messages(&connection, &MessageSearch::new("STACK", "", 10))
+                .unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].account_name, "Foo.1234");
+
+        let messages =
+            ChatDatabase::query_messages(&connection, &MessageSearch::new("0%", "", 10)).unwrap();
+        assert_eq!(messages.len(), 1);
+
+        let messages =
+            ChatDatabase::query_messages(&connection, &MessageSearch::new("", "bar", 10)).unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].text, "100% ready");
+
+        let search = MessageSearch {
+            channel: Some("Squad".to_owned()),
+            account: "foo".to_owned(),
+            ..MessageSearch::new("", "", 10)
+        };
+        let messages = ChatDatabase::query_messages(&connection, &search).unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].character_name, "Foo");
+
+        // 2023-01-05 12:00:30 UTC
+        let search = MessageSearch {
+            after: Some(1672920030),
+            subgroup: Some(0),
+            ..MessageSearch::new("", "", 10)
+        };
+        let messages = ChatDatabase::query_messages(&connection, &search).unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].text, "100% ready");
+    }
+}
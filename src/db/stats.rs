@@ -0,0 +1,199 @@
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use log::error;
+use rusqlite::{params, Connection};
+
+use super::{
+    query::{DbQuery, Queried},
+    stored_timestamp, ChatDatabase,
+};
+
+/// Restricts a statistics query to messages sent at or after `?1`, which is bound as NULL
+/// without a window. The column is compared directly so the timestamp index can be used.
+fn window_clause(since: Option<DateTime<Utc>>) -> &'static str {
+    match since {
+        Some(_) => "timestamp >= ?1",
+        None => "?1 IS NULL",
+    }
+}
+
+#[derive(Clone)]
+pub struct StatsQuery {
+    /// Only count messages sent at or after this unix timestamp.
+    pub since: Option<i64>,
+    /// Maximum number of accounts to return in [`ChatStats::per_account`].
+    pub top_limit: usize,
+}
+
+impl StatsQuery {
+    pub fn new(since: Option<i64>, top_limit: usize) -> Self {
+        Self { since, top_limit }
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct ChatStats {
+    pub total: i64,
+    /// Message counts per account, most active first.
+    pub per_account: Vec<(String, i64)>,
+    /// Message counts per channel type, most active first.
+    pub per_channel: Vec<(String, i64)>,
+    /// Message counts per local hour of day.
+    pub per_hour: [i64; 24],
+    /// Message counts per game session, keyed by session start time and newest first.
+    pub per_session: Vec<(i64, i64)>,
+}
+
+impl ChatDatabase {
+    /// Computes message statistics on the query thread. `result` is set to
+    /// `Queried::Pending` until the query completes.
+    pub fn query_stats(&self, query: StatsQuery, result: &Arc<Mutex<Queried<ChatStats>>>) {
+        *result.lock().unwrap() = Queried::Pending;
+        if let Some(query_channel) = &self.query_channel {
+            if let Err(err) = query_channel
+                .lock()
+                .unwrap()
+                .send(DbQuery::Stats(query, result.clone()))
+            {
+                error!("failed to send query: {:#}", err);
+                *result.lock().unwrap() = Queried::Error;
+            }
+        }
+    }
+
+    pub(crate) fn compute_stats(
+        connection: &Connection,
+        query: &StatsQuery,
+    ) -> rusqlite::Result<ChatStats> {
+        let since = query.since.and_then(stored_timestamp);
+        let mut stats = ChatStats {
+            total: connection.query_row(
+                &format!(
+                    "SELECT COUNT(*) FROM messages WHERE {}",
+                    window_clause(since)
+                ),
+                params![since],
+                |row| row.get(0),
+            )?,
+            per_account: Self::messages_per_account(connection, since, query.top_limit)?,
+            per_channel: Self::count_grouped(connection, "channel_type", since)?,
+            ..Default::default()
+        };
+        for (hour, count) in
+            Self::count_grouped(connection, "strftime('%H', timestamp, 'localtime')", since)?
+        {
+            if let Some(bucket) = hour
+                .parse::<usize>()
+                .ok()
+                .and_then(|hour| stats.per_hour.get_mut(hour))
+            {
+                *bucket += count;
+            }
+        }
+        let mut statement = connection.prepare_cached(&format!(
+            "SELECT game_start, COUNT(*) FROM messages WHERE {}
+            GROUP BY game_start ORDER BY game_start DESC",
+            window_clause(since)
+        ))?;
+        stats.per_session = statement
+            .query_map(params![since], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(stats)
+    }
+
+    /// Returns the accounts that sent the most messages, most active first.
+    pub(crate) fn messages_per_account(
+        connection: &Connection,
+        since: Option<DateTime<Utc>>,
+        limit: usize,
+    ) -> rusqlite::Result<Vec<(String, i64)>> {
+        let mut statement = connection.prepare_cached(&format!(
+            "SELECT account_name, COUNT(*) AS count FROM messages WHERE {}
+            GROUP BY account_name ORDER BY count DESC, account_name LIMIT ?2",
+            window_clause(since)
+        ))?;
+        let counts = statement
+            .query_map(params![since, limit as i64], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })?
+            .collect();
+        counts
+    }
+
+    fn count_grouped(
+        connection: &Connection,
+        expression: &str,
+        since: Option<DateTime<Utc>>,
+    ) -> rusqlite::Result<Vec<(String, i64)>> {
+        let mut statement = connection.prepare_cached(&format!(
+            "SELECT {expression} AS key, COUNT(*) AS count FROM messages
+            WHERE {} AND key IS NOT NULL
+            GROUP BY key ORDER BY count DESC, key",
+            window_clause(since)
+        ))?;
+        let counts = statement
+            .query_map(params![since], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect();
+        counts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_connection;
+
+    fn connection_with_messages() -> Connection {
+        let connection = test_connection();
+        connection
+            .execute_batch(
+                "INSERT INTO messages VALUES (0, 'Squad', 255, 1, '2023-01-05 12:00:00+00:00', 'Foo.1234', 'Foo', 'stack', 1);
+                INSERT INTO messages VALUES (0, 'Squad', 255, 0, '2023-01-05 12:05:00+00:00', 'Foo.1234', 'Foo', 'pull', 1);
+                INSERT INTO messages VALUES (0, 'Party', 0, 0, '2023-01-06 18:00:00+00:00', 'Bar.5678', 'Bar', 'ready', 2);",
+            )
+            .unwrap();
+        connection
+    }
+
+    #[test]
+    fn test_compute_stats() {
+        let connection = connection_with_messages();
+        let stats = ChatDatabase::compute_stats(&connection, &StatsQuery::new(None, 10)).unwrap();
+        assert_eq!(stats.total, 3);
+        assert_eq!(
+            stats.per_account,
+            vec![("Foo.1234".to_owned(), 2), ("Bar.5678".to_owned(), 1)]
+        );
+        assert_eq!(
+            stats.per_channel,
+            vec![("Squad".to_owned(), 2), ("Party".to_owned(), 1)]
+        );
+        assert_eq!(stats.per_hour.iter().sum::<i64>(), 3);
+        assert_eq!(stats.per_session, vec![(2, 1), (1, 2)]);
+    }
+
+    #[test]
+    fn test_compute_stats_window() {
+        let connection = connection_with_messages();
+        // 2023-01-06 00:00:00 UTC
+        let stats = ChatDatabase::compute_stats(&connection, &StatsQuery::new(Some(1672963200), 1))
+            .unwrap();
+        assert_eq!(stats.total, 1);
+        assert_eq!(stats.per_account, vec![("Bar.5678".to_owned(), 1)]);
+        assert_eq!(stats.per_session, vec![(2, 1)]);
+
+        let since = stored_timestamp(1672963200);
+        let plan: String = connection
+            .query_row(
+                &format!(
+                    "EXPLAIN QUERY PLAN SELECT COUNT(*) FROM messages WHERE {}",
+                    window_clause(since)
+                ),
+                params![since],
+                |row| row.get(3),
+            )
+            .unwrap();
+        assert!(plan.starts_with("SEARCH"), "{}", plan);
+    }
+}
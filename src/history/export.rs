@@ -0,0 +1,260 @@
+use std::{
+    borrow::Cow,
+    collections::{hash_map::RandomState, HashMap, HashSet},
+    fs,
+    hash::BuildHasher,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use regex::{Regex, RegexBuilder};
+
+use crate::{db::query::StoredMessage, tracking};
+
+const DEFAULT_EXPORT_DIR: &str = "arcdps_chat_log_exports";
+const EXPORT_TIME_FORMAT: &str = "%Y%m%d-%H%M%S";
+const DATETIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Replaces account and character names with pseudonyms derived from a random salt.
+/// Each name gets the same pseudonym for the lifetime of the anonymiser, so one export
+/// is internally consistent while separate exports can't be correlated.
+pub struct Anonymiser {
+    salt: RandomState,
+    pseudonyms: HashMap<String, String>,
+    used: HashSet<String>,
+    pattern: Option<Regex>,
+}
+
+impl Anonymiser {
+    pub fn new() -> Self {
+        Self {
+            salt: RandomState::new(),
+            pseudonyms: HashMap::new(),
+            used: HashSet::new(),
+            pattern: None,
+        }
+    }
+
+    /// Builds an anonymiser that knows every name appearing in `messages`, as well as the
+    /// `known_accounts` and `known_characters` that may only be named inside message text.
+    pub fn from_messages<'a>(
+        messages: &[StoredMessage],
+        known_accounts: impl IntoIterator<Item = &'a str>,
+        known_characters: impl IntoIterator<Item = &'a str>,
+    ) -> Self {
+        let mut anonymiser = Self::new();
+        for message in messages {
+            anonymiser.add_account(&message.account_name);
+            anonymiser.add_character(&message.character_name);
+        }
+        for account_name in known_accounts {
+            anonymiser.add_account(account_name);
+        }
+        for character_name in known_characters {
+            anonymiser.add_character(character_name);
+        }
+        anonymiser.build_pattern();
+        anonymiser
+    }
+
+    pub fn add_account(&mut self, account_name: &str) {
+        let pseudonym = self.add_name("Account", account_name).to_owned();
+        if let Some(display_name) = tracking::display_name(account_name) {
+            self.pseudonyms
+                .entry(display_name.to_lowercase())
+                .or_insert(pseudonym);
+        }
+    }
+
+    pub fn add_character(&mut self, character_name: &str) {
+        self.add_name("Character", character_name);
+    }
+
+    fn add_name(&mut self, kind: &str, name: &str) -> &str {
+        let key = name.to_lowercase();
+        if !self.pseudonyms.contains_key(&key) {
+            let hash = self.salt.hash_one(&key);
+            let mut pseudonym = format!("{}-{:06x}", kind, hash & 0xff_ffff);
+            let mut suffix = 1;
+            while self.used.contains(&pseudonym) {
+                suffix += 1;
+                pseudonym = format!("{}-{:06x}-{}", kind, hash & 0xff_ffff, suffix);
+            }
+            self.used.insert(pseudonym.clone());
+            self.pseudonyms.insert(key.clone(), pseudonym);
+        }
+        &self.pseudonyms[&key]
+    }
+
+    /// Compiles the pattern used by [`Anonymiser::text`]. Must be called after adding names.
+    pub fn build_pattern(&mut self) {
+        let mut names: Vec<&String> = self.pseudonyms.keys().collect();
+        // longest first, so a character name isn't partially replaced by an account display name
+        names.sort_by(|a, b| b.len().cmp(&a.len()).then(a.cmp(b)));
+        if names.is_empty() {
+            self.pattern = None;
+            return;
+        }
+        let alternation = itertools::join(names.iter().map(|name| regex::escape(name)), "|");
+        self.pattern = RegexBuilder::new(&format!(r"\b(?:{})\b", alternation))
+            .case_insensitive(true)
+            .build()
+            .ok();
+    }
+
+    pub fn name<'a>(&'a self, name: &'a str) -> &'a str {
+        self.pseudonyms
+            .get(&name.to_lowercase())
+            .map_or(name, String::as_str)
+    }
+
+    /// Replaces every known name inside `text`.
+    pub fn text<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        match &self.pattern {
+            Some(pattern) => pattern.replace_all(text, |captures: &regex::Captures| {
+                self.name(&captures[0]).to_owned()
+            }),
+            None => Cow::Borrowed(text),
+        }
+    }
+}
+
+impl Default for Anonymiser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Formats a stored message as shown in the history browser, without the timestamp.
+pub fn message_line(message: &StoredMessage, anonymiser: Option<&Anonymiser>) -> String {
+    match anonymiser {
+        Some(anonymiser) => format!(
+            "{} {} ({}): {}",
+            message.channel_label(),
+            anonymiser.name(&message.character_name),
+            anonymiser.name(&message.account_name),
+            anonymiser.text(&message.text)
+        ),
+        None => format!(
+            "{} {} ({}): {}",
+            message.channel_label(),
+            message.character_name,
+            message.account_name,
+            message.text
+        ),
+    }
+}
+
+/// Returns the folder exports are written to, falling back to a folder next to the database.
+pub fn export_dir(export_path: &str, log_path: &str) -> PathBuf {
+    if !export_path.is_empty() {
+        return PathBuf::from(export_path);
+    }
+    let mut path = Path::new(log_path)
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+    path.push(DEFAULT_EXPORT_DIR);
+    path
+}
+
+/// Writes `messages` oldest first to a new text file in `dir`, returning its path.
+pub fn export_messages(
+    dir: &Path,
+    messages: &[StoredMessage],
+    anonymiser: Option<&Anonymiser>,
+) -> anyhow::Result<PathBuf> {
+    fs::create_dir_all(dir).context("failed to create export folder")?;
+    let now = chrono::Local::now();
+    let path = dir.join(format!(
+        "chat-{}{}.txt",
+        now.format(EXPORT_TIME_FORMAT),
+        if anonymiser.is_some() {
+            "-anonymised"
+        } else {
+            ""
+        }
+    ));
+    let mut file = fs::File::create(&path).context("failed to create export file")?;
+    for message in messages.iter().rev() {
+        let timestamp = message
+            .timestamp()
+            .map(|timestamp| timestamp.format(DATETIME_FORMAT).to_string())
+            .unwrap_or_default();
+        writeln!(
+            file,
+            "[{}] {}",
+            timestamp,
+            message_line(message, anonymiser)
+        )
+        .context("failed to write export file")?;
+    }
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(account_name: &str, character_name: &str, text: &str) -> StoredMessage {
+        StoredMessage {
+            id: 0,
+            channel_type: "Squad".to_owned(),
+            subgroup: 255,
+            is_broadcast: false,
+            timestamp: None,
+            account_name: account_name.to_owned(),
+            character_name: character_name.to_owned(),
+            text: text.to_owned(),
+            game_start: 0,
+        }
+    }
+
+    #[test]
+    fn test_anonymiser() {
+        let messages = vec![
+            message("Foo.1234", "Foo Bar", "hi Baz Qux, stack on me"),
+            message("Baz.5678", "Baz Qux", "thanks foo bar"),
+        ];
+        let anonymiser = Anonymiser::from_messages(&messages, ["Quux.9012"], ["Corge"]);
+
+        let foo = anonymiser.name("Foo.1234");
+        let foo_bar = anonymiser.name("Foo Bar");
+        let baz_qux = anonymiser.name("Baz Qux");
+        assert!(foo.starts_with("Account-"));
+        assert!(foo_bar.starts_with("Character-"));
+        assert_ne!(foo_bar, baz_qux);
+        // stable within one anonymiser, regardless of case
+        assert_eq!(anonymiser.name("foo bar"), foo_bar);
+
+        assert_eq!(
+            anonymiser.text("hi Baz Qux, stack on me"),
+            format!("hi {}, stack on me", baz_qux)
+        );
+        assert_eq!(
+            anonymiser.text("thanks foo bar"),
+            format!("thanks {}", foo_bar)
+        );
+        // account display names are replaced, but not inside other words
+        assert_eq!(anonymiser.text("ask foo"), format!("ask {}", foo));
+        assert_eq!(anonymiser.text("food"), "food");
+
+        let line = message_line(&messages[1], Some(&anonymiser));
+        assert!(!line.contains("Baz"));
+        assert!(!line.to_lowercase().contains("foo"));
+
+        // known names that never sent a message are still hidden in text
+        let quux = anonymiser.name("Quux.9012");
+        assert!(quux.starts_with("Account-"));
+        assert_eq!(
+            anonymiser.text("Quux.9012 and corge are late, ping quux"),
+            format!(
+                "{} and {} are late, ping {}",
+                quux,
+                anonymiser.name("Corge"),
+                quux
+            )
+        );
+    }
+}
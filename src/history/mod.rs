@@ -0,0 +1,275 @@
+use std::sync::{Arc, Mutex};
+
+use anyhow::Context;
+use arcdps::extras::message::ChannelType;
+use log::error;
+
+use crate::{
+    db::{
+        bookmarks::Bookmark,
+        query::{MessageSearch, Queried, StoredMessage},
+        stats::{ChatStats, StatsQuery},
+        ChatDatabase,
+    },
+    logui::{buffer::LogType, query::FilterQuery},
+    tracking::Tracker,
+};
+
+use self::{export::Anonymiser, settings::HistorySettings};
+
+mod export;
+mod settings;
+mod ui;
+
+type QueriedMessages = Arc<Mutex<Queried<Vec<StoredMessage>>>>;
+
+/// Time windows offered by the statistics tab, in seconds before now.
+const STATS_WINDOWS: [(&str, Option<i64>); 5] = [
+    ("Last hour", Some(60 * 60)),
+    ("Last 24 hours", Some(24 * 60 * 60)),
+    ("Last 7 days", Some(7 * 24 * 60 * 60)),
+    ("Last 30 days", Some(30 * 24 * 60 * 60)),
+    ("All time", None),
+];
+
+struct LocalProps {
+    pub text_filter: String,
+    pub user_filter: String,
+    pub secondary_path: String,
+    pub secondary_error: Option<String>,
+    pub results: Option<QueriedMessages>,
+    pub secondary_results: Option<QueriedMessages>,
+    pub stats_window: usize,
+    pub stats_use_secondary: bool,
+    pub stats: Option<Arc<Mutex<Queried<ChatStats>>>>,
+    pub bookmarks: Option<Arc<Mutex<Queried<Vec<Bookmark>>>>>,
+    pub export_status: Option<Result<String, String>>,
+    pub search_error: Option<String>,
+}
+
+impl LocalProps {
+    pub fn new() -> Self {
+        Self {
+            text_filter: String::new(),
+            user_filter: String::new(),
+            secondary_path: String::new(),
+            secondary_error: None,
+            results: None,
+            secondary_results: None,
+            stats_window: 1,
+            stats_use_secondary: false,
+            stats: None,
+            bookmarks: None,
+            export_status: None,
+            search_error: None,
+        }
+    }
+}
+
+/// Browser for messages stored in the chat database, optionally side by side
+/// with a second database opened read-only.
+pub struct HistoryUi {
+    pub settings: HistorySettings,
+    pub chat_database: Option<Arc<Mutex<ChatDatabase>>>,
+    secondary_database: Option<ChatDatabase>,
+    ui_props: LocalProps,
+}
+
+impl HistoryUi {
+    pub fn new() -> Self {
+        Self {
+            settings: HistorySettings::new(),
+            chat_database: None,
+            secondary_database: None,
+            ui_props: LocalProps::new(),
+        }
+    }
+
+    /// Points the browser at a different live database, dropping results from the old one.
+    pub fn set_chat_database(&mut self, chat_database: Option<Arc<Mutex<ChatDatabase>>>) {
+        self.chat_database = chat_database;
+        self.clear_results();
+    }
+
+    /// Drops results from the live database so stale rows aren't shown.
+    pub fn clear_results(&mut self) {
+        self.ui_props.results = None;
+        self.ui_props.stats = None;
+        self.ui_props.bookmarks = None;
+    }
+
+    pub fn open_secondary_database(&mut self) {
+        self.close_secondary_database();
+        match ChatDatabase::try_open_read_only(&self.ui_props.secondary_path)
+            .context("failed to open read-only database")
+        {
+            Ok(chat_database) => {
+                self.secondary_database = Some(chat_database);
+                self.ui_props.secondary_error = None;
+                self.search();
+            }
+            Err(err) => {
+                error!("{:#}", err);
+                self.ui_props.secondary_error = Some(format!("{:#}", err));
+            }
+        }
+    }
+
+    pub fn close_secondary_database(&mut self) {
+        if let Some(mut chat_database) = self.secondary_database.take() {
+            chat_database.release();
+        }
+        self.ui_props.secondary_results = None;
+    }
+
+    pub fn release(&mut self) {
+        self.close_secondary_database();
+    }
+
+    fn refresh_stats(&mut self) {
+        let query = StatsQuery::new(
+            STATS_WINDOWS[self.ui_props.stats_window]
+                .1
+                .map(|window| chrono::Utc::now().timestamp() - window),
+            self.settings.top_talkers.max(1) as usize,
+        );
+        let results = Arc::new(Mutex::new(Queried::Pending));
+        match (&self.secondary_database, &self.chat_database) {
+            (Some(secondary_database), _) if self.ui_props.stats_use_secondary => {
+                secondary_database.query_stats(query, &results)
+            }
+            (_, Some(chat_database)) => chat_database.lock().unwrap().query_stats(query, &results),
+            _ => {
+                self.ui_props.stats = None;
+                return;
+            }
+        }
+        self.ui_props.stats = Some(results);
+    }
+
+    /// Writes the current results of the live or read-only database to a text file.
+    fn export(&mut self, secondary: bool, tracker: &Tracker) {
+        let (results, log_path) = if secondary {
+            (
+                &self.ui_props.secondary_results,
+                self.secondary_database
+                    .as_ref()
+                    .map(|chat_database| chat_database.log_path.clone()),
+            )
+        } else {
+            (
+                &self.ui_props.results,
+                self.chat_database
+                    .as_ref()
+                    .map(|chat_database| chat_database.lock().unwrap().log_path.clone()),
+            )
+        };
+        let (Some(results), Some(log_path)) = (results, log_path) else {
+            return;
+        };
+        let Queried::Success(messages) = &*results.lock().unwrap() else {
+            return;
+        };
+        let dir = export::export_dir(&self.settings.export_path, &log_path);
+        // people can be named in messages without having sent any of the exported ones
+        let anonymiser = self.settings.anonymise_exports.then(|| {
+            let noted_accounts = self.noted_accounts(secondary);
+            Anonymiser::from_messages(
+                messages,
+                tracker
+                    .seen_users
+                    .keys()
+                    .chain(&noted_accounts)
+                    .map(String::as_str),
+                tracker.seen_users.values().flatten().map(String::as_str),
+            )
+        });
+        self.ui_props.export_status = Some(
+            match export::export_messages(&dir, messages, anonymiser.as_ref())
+                .context("failed to export messages")
+            {
+                Ok(path) => Ok(format!(
+                    "Exported {} messages to {}",
+                    messages.len(),
+                    path.display()
+                )),
+                Err(err) => {
+                    error!("{:#}", err);
+                    Err(format!("{:#}", err))
+                }
+            },
+        );
+    }
+
+    /// Accounts with notes in the live database, and in the read-only one when exporting
+    /// from it.
+    fn noted_accounts(&self, secondary: bool) -> Vec<String> {
+        let mut accounts = Vec::new();
+        let live = self
+            .chat_database
+            .as_ref()
+            .map(|chat_database| chat_database.lock().unwrap().noted_accounts());
+        let secondary = self
+            .secondary_database
+            .as_ref()
+            .filter(|_| secondary)
+            .map(ChatDatabase::noted_accounts);
+        for result in live.into_iter().chain(secondary) {
+            match result {
+                Ok(noted) => accounts.extend(noted),
+                Err(err) => error!("{:#}", err),
+            }
+        }
+        accounts
+    }
+
+    /// Builds a database search from the filter query syntax used by the squad log.
+    fn message_search(&mut self) -> MessageSearch {
+        let query = FilterQuery::parse(&self.ui_props.text_filter, chrono::Local::now());
+        let mut search = MessageSearch {
+            account: query.account.clone().unwrap_or_default(),
+            character: query.character.clone().unwrap_or_default(),
+            channel: query.channel.as_ref().map(ToString::to_string),
+            subgroup: query.subgroup.map(|subgroup| subgroup - 1),
+            after: query.after.map(|after| after.timestamp()),
+            before: query.before.map(|before| before.timestamp()),
+            ..MessageSearch::new(
+                &query.text,
+                query.from.as_deref().unwrap_or(&self.ui_props.user_filter),
+                self.settings.result_limit.max(1) as usize,
+            )
+        };
+        let mut error = query.error().map(str::to_owned);
+        match query.log_type {
+            Some(LogType::SquadMessage) => search.channel = Some(ChannelType::Squad.to_string()),
+            Some(LogType::PartyMessage) => search.channel = Some(ChannelType::Party.to_string()),
+            Some(_) => error = Some("only type:squad and type:party are stored".to_owned()),
+            None => {}
+        }
+        self.ui_props.search_error = error;
+        search
+    }
+
+    fn search(&mut self) {
+        let search = self.message_search();
+        self.ui_props.results = self.chat_database.as_ref().map(|chat_database| {
+            let results = Arc::new(Mutex::new(Queried::Pending));
+            chat_database
+                .lock()
+                .unwrap()
+                .search_messages(search.clone(), &results);
+            results
+        });
+        self.ui_props.secondary_results = self.secondary_database.as_ref().map(|chat_database| {
+            let results = Arc::new(Mutex::new(Queried::Pending));
+            chat_database.search_messages(search.clone(), &results);
+            results
+        });
+    }
+}
+
+impl Default for HistoryUi {
+    fn default() -> Self {
+        Self::new()
+    }
+}
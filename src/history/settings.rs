@@ -0,0 +1,44 @@
+use arc_util::settings::HasSettings;
+use serde::{Deserialize, Serialize};
+
+use super::HistoryUi;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HistorySettings {
+    pub result_limit: i32,
+    pub top_talkers: i32,
+    pub export_path: String,
+    pub anonymise_exports: bool,
+}
+
+impl HistorySettings {
+    pub fn new() -> Self {
+        Self {
+            result_limit: 500,
+            top_talkers: 20,
+            export_path: String::new(),
+            anonymise_exports: true,
+        }
+    }
+}
+
+impl Default for HistorySettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HasSettings for HistoryUi {
+    type Settings = HistorySettings;
+
+    const SETTINGS_ID: &'static str = "history";
+
+    fn current_settings(&self) -> Self::Settings {
+        self.settings.clone()
+    }
+
+    fn load_settings(&mut self, loaded: Self::Settings) {
+        self.settings = loaded;
+    }
+}
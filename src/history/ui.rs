@@ -0,0 +1,472 @@
+use std::sync::{Arc, Mutex};
+
+use arc_util::ui::{
+    render::{self, item_context_menu},
+    Component, Ui, Windowable,
+};
+use arcdps::{
+    exports::{self, CoreColor},
+    imgui::ChildFlags,
+};
+use chrono::TimeZone;
+use log::error;
+
+use crate::{
+    db::{
+        bookmarks::{BookmarkTarget, BookmarkToAdd},
+        query::{Queried, StoredMessage},
+        ChatDatabase,
+    },
+    tracking::Tracker,
+};
+
+use super::{export, HistoryUi, QueriedMessages, STATS_WINDOWS};
+
+const DATETIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+impl Windowable<&Tracker> for HistoryUi {
+    const CONTEXT_MENU: bool = true;
+    const DEFAULT_OPTIONS: bool = true;
+
+    fn render_menu(&mut self, ui: &Ui, _props: &mut &Tracker) {
+        ui.set_next_item_width(render::ch_width(ui, 10));
+        ui.input_int("Maximum results", &mut self.settings.result_limit)
+            .build();
+        ui.set_next_item_width(render::ch_width(ui, 10));
+        ui.input_int("Top talkers shown", &mut self.settings.top_talkers)
+            .build();
+        ui.set_next_item_width(render::ch_width(ui, 40));
+        ui.input_text("Export folder", &mut self.settings.export_path)
+            .hint("Next to the database")
+            .build();
+        ui.separator();
+    }
+}
+
+impl Component<&Tracker> for HistoryUi {
+    fn render(&mut self, ui: &Ui, tracker: &Tracker) {
+        let _style = render::small_padding(ui);
+        if let Some(_tab_bar) = ui.tab_bar("history_tabs") {
+            if let Some(_tab) = ui.tab_item("Search") {
+                self.render_search(ui, tracker);
+            }
+            if let Some(_tab) = ui.tab_item("Bookmarks") {
+                self.render_bookmarks(ui);
+            }
+            if let Some(_tab) = ui.tab_item("Statistics") {
+                self.render_stats(ui);
+            }
+        }
+    }
+}
+
+impl HistoryUi {
+    fn render_search(&mut self, ui: &Ui, tracker: &Tracker) {
+        let colors = exports::colors();
+        let red = colors
+            .core(CoreColor::LightRed)
+            .unwrap_or([1.0, 0.0, 0.0, 1.0]);
+
+        let mut search = false;
+        ui.set_next_item_width(render::ch_width(ui, 24));
+        search |= ui
+            .input_text("Text", &mut self.ui_props.text_filter)
+            .enter_returns_true(true)
+            .build();
+        if ui.is_item_hovered() {
+            ui.tooltip_text(
+                "Supports from:, account:, char:, channel:, sub:, type:, after: and before:\nfor example: from:Foo.1234 channel:party sub:3 \"stack\"",
+            );
+        }
+        ui.same_line();
+        ui.set_next_item_width(render::ch_width(ui, 16));
+        search |= ui
+            .input_text("User", &mut self.ui_props.user_filter)
+            .enter_returns_true(true)
+            .build();
+        if ui.is_item_hovered() {
+            ui.tooltip_text("Account or character name");
+        }
+        ui.same_line();
+        search |= ui.button("Search");
+        if search {
+            self.search();
+        }
+        if let Some(search_error) = &self.ui_props.search_error {
+            ui.text_colored(red, search_error);
+        }
+
+        match &self.secondary_database {
+            Some(secondary_database) => {
+                ui.text(format!(
+                    "Comparing with {} (read-only)",
+                    secondary_database.log_path
+                ));
+                ui.same_line();
+                if ui.button("Close") {
+                    self.close_secondary_database();
+                }
+            }
+            None => {
+                ui.set_next_item_width(render::ch_width(ui, 40));
+                ui.input_text("##secondarypath", &mut self.ui_props.secondary_path)
+                    .hint("Path to another chat database")
+                    .build();
+                ui.same_line();
+                if ui.button("Open read-only") {
+                    self.open_secondary_database();
+                }
+                if let Some(secondary_error) = &self.ui_props.secondary_error {
+                    ui.text_colored(red, secondary_error);
+                }
+            }
+        }
+
+        ui.checkbox("Anonymise names", &mut self.settings.anonymise_exports);
+        if ui.is_item_hovered() {
+            ui.tooltip_text(
+                "Replace account and character names with pseudonyms, including inside messages",
+            );
+        }
+        ui.same_line();
+        if ui.button("Export results") {
+            self.export(false, tracker);
+        }
+        if self.secondary_database.is_some() {
+            ui.same_line();
+            if ui.button("Export read-only results") {
+                self.export(true, tracker);
+            }
+        }
+        match &self.ui_props.export_status {
+            Some(Ok(status)) => ui.text_disabled(status),
+            Some(Err(err)) => ui.text_colored(red, err),
+            None => {}
+        }
+
+        if let Some(_child) = ui.child_window("history_results").begin() {
+            let live_label = match &self.chat_database {
+                Some(chat_database) => format!("Live: {}", chat_database.lock().unwrap().log_path),
+                None => "Live: database not available".to_owned(),
+            };
+            match &self.secondary_database {
+                Some(secondary_database) => {
+                    let width = (ui.content_region_avail()[0] - 4.0) / 2.0;
+                    HistoryUi::render_results(
+                        ui,
+                        "history_results_live",
+                        &live_label,
+                        &self.ui_props.results,
+                        self.chat_database.as_ref(),
+                        width,
+                    );
+                    ui.same_line_with_spacing(0.0, 4.0);
+                    HistoryUi::render_results(
+                        ui,
+                        "history_results_secondary",
+                        &format!("Read-only: {}", secondary_database.log_path),
+                        &self.ui_props.secondary_results,
+                        None,
+                        0.0,
+                    );
+                }
+                None => HistoryUi::render_results(
+                    ui,
+                    "history_results_live",
+                    &live_label,
+                    &self.ui_props.results,
+                    self.chat_database.as_ref(),
+                    0.0,
+                ),
+            }
+        }
+    }
+
+    fn render_bookmarks(&mut self, ui: &Ui) {
+        let Some(chat_database) = &self.chat_database else {
+            ui.text_disabled("Database not available");
+            return;
+        };
+        let colors = exports::colors();
+        let red = colors
+            .core(CoreColor::LightRed)
+            .unwrap_or([1.0, 0.0, 0.0, 1.0]);
+
+        let bookmarks = self
+            .ui_props
+            .bookmarks
+            .get_or_insert_with(|| {
+                let bookmarks = Arc::new(Mutex::new(Queried::Pending));
+                chat_database.lock().unwrap().query_bookmarks(&bookmarks);
+                bookmarks
+            })
+            .clone();
+        if ui.button("Refresh") {
+            chat_database.lock().unwrap().query_bookmarks(&bookmarks);
+        }
+
+        let mut bookmarks = bookmarks.lock().unwrap();
+        let bookmarks = match &mut *bookmarks {
+            Queried::Pending => {
+                ui.text_disabled("Loading");
+                return;
+            }
+            Queried::Error => {
+                ui.text_colored(red, "Failed to load bookmarks - check the logs");
+                return;
+            }
+            Queried::Success(bookmarks) if bookmarks.is_empty() => {
+                ui.text_disabled("No bookmarks - right click a message to bookmark it");
+                return;
+            }
+            Queried::Success(bookmarks) => bookmarks,
+        };
+
+        let mut deleted = None;
+        if let Some(_child) = ui.child_window("history_bookmarks").begin() {
+            for bookmark in bookmarks.iter_mut() {
+                let message_id = bookmark.message.id;
+                ui.separator();
+                let timestamp = bookmark
+                    .message
+                    .timestamp()
+                    .map(|timestamp| timestamp.format(DATETIME_FORMAT).to_string())
+                    .unwrap_or_default();
+                ui.text_disabled(format!("[{}]", timestamp));
+                ui.same_line();
+                ui.text_wrapped(export::message_line(&bookmark.message, None));
+                if ui.is_item_hovered() {
+                    ui.tooltip_text(format!(
+                        "Bookmarked: {}",
+                        bookmark.created().format(DATETIME_FORMAT)
+                    ));
+                }
+                ui.set_next_item_width(render::ch_width(ui, 40));
+                ui.input_text(
+                    format!("##annotation{}", message_id),
+                    &mut bookmark.annotation,
+                )
+                .hint("Annotation")
+                .build();
+                if ui.is_item_deactivated_after_edit() {
+                    if let Err(err) = chat_database
+                        .lock()
+                        .unwrap()
+                        .update_bookmark(message_id, &bookmark.annotation)
+                    {
+                        error!("failed to update bookmark: {:#}", err);
+                    }
+                }
+                ui.same_line();
+                if ui.button(format!("Delete##bookmark{}", message_id)) {
+                    deleted = Some(message_id);
+                }
+            }
+        }
+        if let Some(message_id) = deleted {
+            if let Err(err) = chat_database.lock().unwrap().delete_bookmark(message_id) {
+                error!("failed to delete bookmark: {:#}", err);
+            }
+            bookmarks.retain(|bookmark| bookmark.message.id != message_id);
+        }
+    }
+
+    fn render_stats(&mut self, ui: &Ui) {
+        let colors = exports::colors();
+        let grey = colors
+            .core(CoreColor::MediumGrey)
+            .unwrap_or([0.5, 0.5, 0.5, 1.0]);
+        let red = colors
+            .core(CoreColor::LightRed)
+            .unwrap_or([1.0, 0.0, 0.0, 1.0]);
+
+        let mut refresh = self.ui_props.stats.is_none();
+        ui.set_next_item_width(render::ch_width(ui, 16));
+        if let Some(_combo) =
+            ui.begin_combo("Time window", STATS_WINDOWS[self.ui_props.stats_window].0)
+        {
+            for (i, (label, _)) in STATS_WINDOWS.iter().enumerate() {
+                if ui
+                    .selectable_config(label)
+                    .selected(i == self.ui_props.stats_window)
+                    .build()
+                {
+                    self.ui_props.stats_window = i;
+                    refresh = true;
+                }
+            }
+        }
+        if let Some(secondary_database) = &self.secondary_database {
+            ui.same_line();
+            refresh |= ui.checkbox(
+                format!("Use {}", secondary_database.log_path),
+                &mut self.ui_props.stats_use_secondary,
+            );
+        } else {
+            self.ui_props.stats_use_secondary = false;
+        }
+        ui.same_line();
+        refresh |= ui.button("Refresh");
+        if refresh {
+            self.refresh_stats();
+        }
+
+        let Some(stats) = &self.ui_props.stats else {
+            ui.text_disabled("Database not available");
+            return;
+        };
+        let stats = stats.lock().unwrap();
+        let stats = match &*stats {
+            Queried::Pending => {
+                ui.text_disabled("Loading");
+                return;
+            }
+            Queried::Error => {
+                ui.text_colored(red, "Failed to compute statistics - check the logs");
+                return;
+            }
+            Queried::Success(stats) => stats,
+        };
+
+        if let Some(_child) = ui.child_window("history_stats").begin() {
+            ui.text(format!("{} messages", stats.total));
+
+            ui.spacing();
+            ui.text_colored(grey, "Messages per hour of day");
+            let per_hour: Vec<f32> = stats.per_hour.iter().map(|x| *x as f32).collect();
+            ui.plot_histogram("##perhour", &per_hour)
+                .scale_min(0.0)
+                .graph_size([ui.content_region_avail()[0], 80.0])
+                .build();
+            if ui.is_item_hovered() {
+                ui.tooltip_text("Hours 00 to 23, local time");
+            }
+
+            ui.spacing();
+            ui.text_colored(grey, "Top talkers");
+            HistoryUi::render_counts(ui, "stats_accounts", "Account", &stats.per_account);
+
+            ui.spacing();
+            ui.text_colored(grey, "Messages per channel");
+            HistoryUi::render_counts(ui, "stats_channels", "Channel", &stats.per_channel);
+
+            ui.spacing();
+            ui.text_colored(grey, "Messages per session");
+            let per_session: Vec<(String, i64)> = stats
+                .per_session
+                .iter()
+                .map(|(game_start, count)| {
+                    let started = chrono::Utc
+                        .timestamp_opt(*game_start, 0)
+                        .single()
+                        .map(|started| {
+                            started
+                                .with_timezone(&chrono::Local)
+                                .format(DATETIME_FORMAT)
+                                .to_string()
+                        })
+                        .unwrap_or_else(|| game_start.to_string());
+                    (started, *count)
+                })
+                .collect();
+            HistoryUi::render_counts(ui, "stats_sessions", "Session started", &per_session);
+        }
+    }
+
+    fn render_counts(ui: &Ui, id: &str, label: &str, counts: &[(String, i64)]) {
+        if let Some(_table) = ui.begin_table(id, 2) {
+            ui.table_setup_column(label);
+            ui.table_setup_column("Messages");
+            ui.table_headers_row();
+            for (key, count) in counts {
+                ui.table_next_row();
+                ui.table_next_column();
+                ui.text(key);
+                ui.table_next_column();
+                ui.text(count.to_string());
+            }
+        }
+    }
+
+    fn render_results(
+        ui: &Ui,
+        id: &str,
+        source: &str,
+        results: &Option<QueriedMessages>,
+        bookmark_database: Option<&Arc<Mutex<ChatDatabase>>>,
+        width: f32,
+    ) {
+        let colors = exports::colors();
+        let grey = colors
+            .core(CoreColor::MediumGrey)
+            .unwrap_or([0.5, 0.5, 0.5, 1.0]);
+        let red = colors
+            .core(CoreColor::LightRed)
+            .unwrap_or([1.0, 0.0, 0.0, 1.0]);
+
+        if let Some(_child) = ui
+            .child_window(id)
+            .child_flags(ChildFlags::BORDERS)
+            .size([width, 0.0])
+            .begin()
+        {
+            ui.text_colored(grey, source);
+            ui.separator();
+            match results {
+                None => ui.text_disabled("Enter a search and press Search"),
+                Some(results) => match &*results.lock().unwrap() {
+                    Queried::Pending => ui.text_disabled("Loading"),
+                    Queried::Error => ui.text_colored(red, "Failed to search - check the logs"),
+                    Queried::Success(messages) if messages.is_empty() => {
+                        ui.text_disabled("No messages found")
+                    }
+                    Queried::Success(messages) => messages.iter().for_each(|message| {
+                        HistoryUi::render_message(ui, id, source, message, bookmark_database)
+                    }),
+                },
+            }
+        }
+    }
+
+    fn render_message(
+        ui: &Ui,
+        id: &str,
+        source: &str,
+        message: &StoredMessage,
+        bookmark_database: Option<&Arc<Mutex<ChatDatabase>>>,
+    ) {
+        let timestamp = message
+            .timestamp()
+            .map(|timestamp| timestamp.format(DATETIME_FORMAT).to_string())
+            .unwrap_or_default();
+        ui.text_disabled(format!("[{}]", timestamp));
+        ui.same_line();
+        let line = export::message_line(message, None);
+        ui.text_wrapped(&line);
+        if ui.is_item_hovered() {
+            ui.tooltip_text(source);
+        }
+        item_context_menu(format!("##{}context{}", id, message.id), || {
+            if ui.button("Copy line") {
+                ui.set_clipboard_text(format!("[{}] {}", timestamp, line));
+                ui.close_current_popup();
+            }
+            if ui.button("Copy text") {
+                ui.set_clipboard_text(&message.text);
+                ui.close_current_popup();
+            }
+            if let Some(chat_database) = bookmark_database {
+                if ui.button("Bookmark message") {
+                    if let Err(err) = chat_database
+                        .lock()
+                        .unwrap()
+                        .add_bookmark(BookmarkToAdd::new(BookmarkTarget::Id(message.id), ""))
+                    {
+                        error!("failed to add bookmark: {:#}", err);
+                    }
+                    ui.close_current_popup();
+                }
+            }
+        });
+    }
+}
@@ -1,10 +1,15 @@
 mod audio;
+mod chatlink;
 mod db;
+mod history;
 mod logui;
+mod mentions;
 mod mumblelink;
 mod notifications;
 mod panic_handler;
 mod plugin;
+mod repeats;
+mod roster;
 mod tracking;
 mod tts;
 mod update;
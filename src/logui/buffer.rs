@@ -1,17 +1,40 @@
-use std::collections::VecDeque;
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::cell::Cell;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::ops::Range;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
 
 use arc_util::ui::{render::item_context_menu, Ui};
 use arcdps::{
     extras::message::{ChannelType, SquadMessageFlags, SquadMessageOwned},
-    imgui::{sys, StyleColor},
+    imgui::{sys, StyleColor, StyleVar},
+    Profession,
 };
 use chrono::Local;
 use core::ffi::c_char;
 
-use super::settings::{ColorSettings, FilterSettings};
+use crate::{
+    chatlink::{self, names::ChatLinkNames},
+    db::query::StoredMessage,
+    repeats,
+};
+
+use super::{
+    filter::TextFilter,
+    highlight::{Highlight, Highlighter},
+    query::FilterQuery,
+    settings::{ColorRole, ColorSettings, FilterSettings, TimestampSettings},
+};
 
 const TIMESTAMP_FORMAT: &str = "%H:%M:%S";
+/// Opacity of lines loaded from the database, to set them apart from this session.
+const HISTORY_ALPHA: f32 = 0.6;
+/// How soon after the last update of a burst the next one is grouped with it.
+const BURST_WINDOW: chrono::Duration = chrono::Duration::seconds(2);
+/// Most characters to remember the profession of, forgetting those seen longest ago.
+const MAX_PROFESSIONS: usize = 1000;
 
 static NEXT_LOG_PART_ID: AtomicU64 = AtomicU64::new(0);
 
@@ -21,7 +44,19 @@ pub struct LogPart {
     pub text: String,
     pub hover: Option<String>,
     pub color: Option<[f32; 4]>,
+    /// Colour taken from the view rendering the part, used when `color` isn't set.
+    pub role: Option<ColorRole>,
+    /// Profession of the character named by the part, which views can colour it by.
+    pub profession: Option<Profession>,
+    pub highlight: Option<Highlight>,
     pub clipboard: Option<String>,
+    /// The chat code this part was decoded from, shown as a readable label instead.
+    pub chat_code: Option<String>,
+    /// Shown when hovering the part, unlike `hover` which is an account name.
+    pub tooltip: Option<String>,
+    /// Set for timestamps, which are formatted when rendered. Timestamps aren't matched
+    /// by text filters, lines are filtered by time with `after:` and `before:` instead.
+    pub time: Option<chrono::DateTime<Local>>,
 }
 
 impl LogPart {
@@ -36,35 +71,84 @@ impl LogPart {
             text: text.to_owned(),
             hover: hover.map(str::to_string),
             color,
+            role: None,
+            profession: None,
+            highlight: None,
             clipboard: clipboard.map(str::to_string),
+            chat_code: None,
+            tooltip: None,
+            time: None,
         }
     }
 
+    /// A part showing `label` for the chat link `code`, which can be copied back into chat.
+    pub fn new_chat_link(label: &str, code: &str, tooltip: &str) -> Self {
+        let mut part = Self::new(label, None, None, None);
+        part.chat_code = Some(code.to_owned());
+        part.tooltip = Some(tooltip.to_owned());
+        part
+    }
+
+    pub fn with_role(mut self, role: Option<ColorRole>) -> Self {
+        self.role = role;
+        self
+    }
+
+    pub fn with_profession(mut self, profession: Option<Profession>) -> Self {
+        self.profession = profession;
+        self
+    }
+
+    pub fn with_highlight(mut self, highlight: Option<Highlight>) -> Self {
+        self.highlight = highlight;
+        self
+    }
+
     pub fn new_no_color(text: &str) -> Self {
         Self::new(text, None, None, None)
     }
 
     pub fn new_time<T: chrono::TimeZone>(time: chrono::DateTime<T>) -> Self {
-        Self::new(
-            &format!("[{}]", time.with_timezone(&Local).format(TIMESTAMP_FORMAT)),
+        let time = time.with_timezone(&Local);
+        let mut part = Self::new(
+            &format!("[{}]", time.format(TIMESTAMP_FORMAT)),
             None,
             None,
             None,
-        )
+        );
+        part.time = Some(time);
+        part
     }
 
     pub fn new_current_time() -> Self {
         Self::new_time(chrono::Local::now())
     }
 
-    pub fn render(&self, ui: &Ui, display_hover: bool, line_text: &str) {
+    /// Renders the part, returning whether the user asked to bookmark the line's message.
+    pub fn render(
+        &self,
+        ui: &Ui,
+        display_hover: bool,
+        line_text: &str,
+        bookmarkable: bool,
+        colors: &ColorSettings,
+        timestamps: &TimestampSettings,
+    ) -> bool {
         let color_style = self
-            .color
+            .highlight
+            .filter(|highlight| !highlight.background)
+            .map(|highlight| highlight.color)
+            .or(self.color)
+            .or_else(|| {
+                self.profession
+                    .and_then(|profession| colors.profession_color(profession))
+            })
+            .or_else(|| self.role.map(|role| colors.color(role)))
             .map(|color| ui.push_style_color(StyleColor::Text, color));
 
         let width_left = ui.content_region_avail()[0];
         let end_length: usize;
-        let label = self.get_text(display_hover);
+        let label = self.get_text(display_hover, timestamps);
         let s: &str = label.as_ref();
 
         unsafe {
@@ -85,12 +169,14 @@ impl LogPart {
             ) as *const u8;
             end_length = end_line.offset_from(start) as usize;
 
-            ui.text(std::str::from_utf8_unchecked(
-                &label.as_bytes()[..end_length],
-            ));
+            self.render_text(
+                ui,
+                std::str::from_utf8_unchecked(&label.as_bytes()[..end_length]),
+                false,
+            );
         }
 
-        self.render_context_menu(ui, 0, line_text);
+        let mut bookmark = self.render_context_menu(ui, 0, line_text, bookmarkable);
         self.render_hover(ui, display_hover);
 
         if end_length < label.len() {
@@ -100,8 +186,8 @@ impl LogPart {
                 if rest_of_str.starts_with(' ') {
                     rest_of_str = &rest_of_str[1..];
                 }
-                ui.text_wrapped(rest_of_str);
-                self.render_context_menu(ui, 1, line_text);
+                self.render_text(ui, rest_of_str, true);
+                bookmark |= self.render_context_menu(ui, 1, line_text, bookmarkable);
                 self.render_hover(ui, display_hover);
             }
         }
@@ -109,9 +195,50 @@ impl LogPart {
         if let Some(color_style) = color_style {
             color_style.pop();
         }
+        bookmark
+    }
+
+    /// Draws the text, with the background and faux bold of the highlight if there is one.
+    fn render_text(&self, ui: &Ui, text: &str, wrapped: bool) {
+        let draw = || {
+            if wrapped {
+                ui.text_wrapped(text);
+            } else {
+                ui.text(text);
+            }
+        };
+        let Some(highlight) = self.highlight else {
+            draw();
+            return;
+        };
+        let pos = ui.cursor_screen_pos();
+        if highlight.background {
+            let wrap_width = if wrapped {
+                ui.content_region_avail()[0]
+            } else {
+                -1.0
+            };
+            let size = ui.calc_text_size_with_opts(text, false, wrap_width);
+            ui.get_window_draw_list()
+                .add_rect(pos, [pos[0] + size[0], pos[1] + size[1]], highlight.color)
+                .filled(true)
+                .build();
+        }
+        if highlight.bold {
+            // there is no bold font, so draw the text a second time one pixel to the right
+            ui.set_cursor_screen_pos([pos[0] + 1.0, pos[1]]);
+            draw();
+            ui.set_cursor_screen_pos(pos);
+        }
+        draw();
     }
 
     fn render_hover(&self, ui: &Ui, display_hover: bool) {
+        if let Some(tooltip) = &self.tooltip {
+            if ui.is_item_hovered() {
+                ui.tooltip_text(tooltip);
+            }
+        }
         if !display_hover {
             return;
         }
@@ -122,38 +249,64 @@ impl LogPart {
         }
     }
 
-    fn render_context_menu(&self, ui: &Ui, order: usize, line_text: &str) {
+    fn render_context_menu(
+        &self,
+        ui: &Ui,
+        order: usize,
+        line_text: &str,
+        bookmarkable: bool,
+    ) -> bool {
+        let mut bookmark = false;
         item_context_menu(format!("##squadlogcontext{}{}", order, self.id), || {
             if ui.button("Copy line") {
                 ui.set_clipboard_text(line_text);
                 ui.close_current_popup();
             }
+            if let Some(code) = self.chat_code.as_ref() {
+                if ui.button("Copy chat link") {
+                    ui.set_clipboard_text(code);
+                    ui.close_current_popup();
+                }
+            }
             if let Some(text) = self.clipboard.as_ref() {
                 if ui.button("Copy") {
                     ui.set_clipboard_text(text);
                     ui.close_current_popup();
                 }
             }
+            if bookmarkable && ui.button("Bookmark message") {
+                bookmark = true;
+                ui.close_current_popup();
+            }
         });
+        bookmark
     }
 
-    pub fn get_text(&self, display_hover: bool) -> String {
+    pub fn get_text(&self, display_hover: bool, timestamps: &TimestampSettings) -> String {
+        if let Some(time) = self.time {
+            return format!("[{}]", timestamps.format(time, Local::now()));
+        }
         match &self.hover {
-            Some(hover) if !display_hover => format!("{} ({})", self.text, hover),
+            Some(hover) if !display_hover && *hover != self.text => {
+                format!("{} ({})", self.text, hover)
+            }
             _ => self.text.clone(),
         }
     }
 
-    pub fn filter(&self, filter: &str) -> bool {
-        self.text.contains(filter)
+    pub fn filter(&self, filter: &TextFilter) -> bool {
+        if self.time.is_some() {
+            return false;
+        }
+        filter.matches(&self.text)
             || match &self.hover {
-                Some(hover) => hover.contains(filter),
+                Some(hover) => filter.matches(hover),
                 None => false,
             }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum LogType {
     Generic,
     SquadMessage,
@@ -162,10 +315,55 @@ pub enum LogType {
     Combat,
 }
 
+/// Updates that come in bursts, like a squad loading into a map or starting a fight,
+/// which are grouped into one summary line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Burst {
+    JoinedInstance,
+    LeftInstance,
+    EnteredCombat,
+    LeftCombat,
+}
+
+impl Burst {
+    fn log_type(self) -> LogType {
+        match self {
+            Burst::JoinedInstance | Burst::LeftInstance => LogType::SquadUpdate,
+            Burst::EnteredCombat | Burst::LeftCombat => LogType::Combat,
+        }
+    }
+
+    fn summary(self, count: usize) -> String {
+        let event = match self {
+            Burst::JoinedInstance => "joined your instance",
+            Burst::LeftInstance => "left your instance",
+            Burst::EnteredCombat => "entered combat",
+            Burst::LeftCombat => "left combat",
+        };
+        let players = if count == 1 { "player" } else { "players" };
+        format!("{} {} {}", count, players, event)
+    }
+}
+
 #[derive(Debug)]
 pub struct LogLine {
     pub parts: Vec<LogPart>,
     pub log_type: LogType,
+    /// When the line was logged, or when the chat message was sent.
+    pub time: chrono::DateTime<Local>,
+    /// The chat message this line was created from, if any, matched by filter queries.
+    pub message: Option<SquadMessageOwned>,
+    /// Whether the line was loaded from the database rather than received this session.
+    pub history: bool,
+    /// How many times the message was sent, when repeats are collapsed into one line.
+    pub repeats: u32,
+    pub last_repeat: Option<chrono::DateTime<Local>>,
+    /// The lines grouped under this one, if it summarises a burst.
+    pub details: Vec<LogLine>,
+    pub burst: Option<Burst>,
+    /// Whether the line follows a ready check, and is replaced until the check is over.
+    pub ready_check: bool,
+    expanded: Cell<bool>,
 }
 
 impl LogLine {
@@ -173,26 +371,113 @@ impl LogLine {
         Self {
             parts: Vec::new(),
             log_type: LogType::Generic,
+            time: Local::now(),
+            message: None,
+            history: false,
+            repeats: 1,
+            last_repeat: None,
+            details: Vec::new(),
+            burst: None,
+            ready_check: false,
+            expanded: Cell::new(false),
         }
     }
 
-    pub fn render(&self, ui: &Ui, hover: bool) {
-        let line_text = self.full_text(hover);
+    /// When the message was last sent, counting repeats.
+    fn last_seen(&self) -> chrono::DateTime<Local> {
+        self.last_repeat.unwrap_or(self.time)
+    }
+
+    /// Renders the line, returning whether the user asked to bookmark its message.
+    /// Bookmarking is only offered for chat messages when `bookmarkable` is set.
+    pub fn render(
+        &self,
+        ui: &Ui,
+        hover: bool,
+        bookmarkable: bool,
+        colors: &ColorSettings,
+        timestamps: &TimestampSettings,
+    ) -> bool {
+        let _alpha = self
+            .history
+            .then(|| ui.push_style_var(StyleVar::Alpha(HISTORY_ALPHA)));
+        let line_text = self.full_text(hover, timestamps);
+        let bookmarkable = bookmarkable && self.message.is_some();
+        let mut bookmark = false;
         self.parts.iter().for_each(|p| {
-            p.render(ui, hover, &line_text);
+            bookmark |= p.render(ui, hover, &line_text, bookmarkable, colors, timestamps);
             ui.same_line_with_spacing(0.0, 0.0);
         });
+        if self.repeats > 1 {
+            ui.text_disabled(format!(" ×{}", self.repeats));
+            if ui.is_item_hovered() {
+                ui.tooltip_text(format!(
+                    "Last sent at {}",
+                    timestamps.format(self.last_seen(), Local::now())
+                ));
+            }
+            ui.same_line_with_spacing(0.0, 0.0);
+        }
+        if !self.details.is_empty() {
+            let _id = ui.push_id_ptr(self);
+            let expanded = self.expanded.get();
+            ui.same_line();
+            if ui.small_button(if expanded { "Hide" } else { "Show" }) {
+                self.expanded.set(!expanded);
+            }
+            ui.same_line_with_spacing(0.0, 0.0);
+        }
         ui.new_line();
+        if self.expanded.get() && !self.details.is_empty() {
+            ui.indent();
+            for line in &self.details {
+                line.render(ui, hover, false, colors, timestamps);
+            }
+            ui.unindent();
+        }
+        bookmark
     }
 
-    fn full_text(&self, display_hover: bool) -> String {
+    /// Replaces the summary of a burst line with the number of accounts grouped under it,
+    /// counting an account once even if it is in the burst more than once.
+    fn summarise_burst(&mut self, burst: Burst) {
+        let accounts = self
+            .details
+            .iter()
+            .flat_map(|line| &line.parts)
+            .filter_map(|part| part.hover.as_deref())
+            .collect::<HashSet<_>>()
+            .len();
+        self.parts.truncate(2);
+        self.parts
+            .push(LogPart::new_no_color(&burst.summary(accounts)));
+    }
+
+    fn mentions_account(&self, account_name: &str) -> bool {
+        self.message
+            .as_ref()
+            .is_some_and(|message| message.account_name == account_name)
+            || self
+                .parts
+                .iter()
+                .any(|part| part.hover.as_deref() == Some(account_name))
+    }
+
+    /// The text of the line, with chat links as their original code so a copied line
+    /// can be pasted back into chat.
+    fn full_text(&self, display_hover: bool, timestamps: &TimestampSettings) -> String {
         self.parts
             .iter()
-            .map(|p| p.get_text(display_hover))
+            .map(|p| match &p.chat_code {
+                Some(code) => code.clone(),
+                None => p.get_text(display_hover, timestamps),
+            })
             .collect()
     }
 
-    pub fn filter(&self, text: &str, types: &FilterSettings) -> bool {
+    /// Whether the line is shown with the given filters. Summaries of bursts are shown if
+    /// any of the lines grouped under them match.
+    pub fn filter(&self, query: &FilterQuery, text: &TextFilter, types: &FilterSettings) -> bool {
         match self.log_type {
             LogType::Generic => {
                 if !types.others {
@@ -220,10 +505,22 @@ impl LogLine {
                 }
             }
         }
+        self.matches(query, text) || self.details.iter().any(|line| line.matches(query, text))
+    }
+
+    fn matches(&self, query: &FilterQuery, text: &TextFilter) -> bool {
+        if !query.matches_line(self) {
+            return false;
+        }
         if text.is_empty() {
             return true;
         }
+        // highlights split the message text over several parts
         self.parts.iter().any(|p| p.filter(text))
+            || self
+                .message
+                .as_ref()
+                .is_some_and(|message| text.matches(&message.text))
     }
 }
 
@@ -231,7 +528,29 @@ impl LogLine {
 pub struct LogBuffer {
     pub buffer: VecDeque<LogLine>,
     pub buffer_max_size: usize,
-    pub colors: ColorSettings,
+    pub highlighter: Highlighter,
+    pub chat_link_names: Arc<ChatLinkNames>,
+    /// Account and profession of each character seen through arc tracking, by character
+    /// name.
+    pub professions: HashMap<String, (String, Profession)>,
+    /// Characters in `professions`, in the order they were last seen.
+    profession_order: VecDeque<String>,
+    /// Number of lines dropped from the front of the buffer, so lines can be referred to
+    /// by an index that doesn't change as older lines are dropped.
+    pub dropped: u64,
+    /// Index of the last chat message line of each account, to collapse repeats into.
+    last_messages: HashMap<String, u64>,
+    pub collapse_repeats: bool,
+    pub repeat_window: chrono::Duration,
+    /// Index of the line each kind of burst was last grouped into.
+    bursts: HashMap<Burst, u64>,
+    pub group_bursts: bool,
+    /// Changed whenever lines are inserted anywhere but the back, or removed anywhere but
+    /// the front, so anything derived from the lines knows to rebuild.
+    pub generation: u64,
+    /// Indices of lines changed in place since they were last taken, which may now pass
+    /// different filters.
+    changed: BTreeSet<u64>,
 }
 
 impl LogBuffer {
@@ -239,47 +558,308 @@ impl LogBuffer {
         Self {
             buffer: VecDeque::new(),
             buffer_max_size: 100,
-            colors: ColorSettings::new(),
+            highlighter: Highlighter::default(),
+            chat_link_names: Arc::new(ChatLinkNames::bundled()),
+            professions: HashMap::new(),
+            profession_order: VecDeque::new(),
+            dropped: 0,
+            last_messages: HashMap::new(),
+            collapse_repeats: true,
+            repeat_window: repeats::REPEAT_WINDOW,
+            bursts: HashMap::new(),
+            group_bursts: true,
+            generation: 0,
+            changed: BTreeSet::new(),
         }
     }
 
+    /// Returns the line at `index`, counting from the first line ever added.
+    pub fn line(&self, index: u64) -> Option<&LogLine> {
+        self.buffer.get(index.checked_sub(self.dropped)? as usize)
+    }
+
+    /// Remembers the profession a character was seen as, forgetting the character seen
+    /// longest ago once there are [`MAX_PROFESSIONS`].
+    pub fn set_profession(
+        &mut self,
+        account_name: &str,
+        character_name: &str,
+        profession: Profession,
+    ) {
+        let previous = self.professions.insert(
+            character_name.to_owned(),
+            (account_name.to_owned(), profession),
+        );
+        if previous.is_some() {
+            self.profession_order.retain(|name| name != character_name);
+        }
+        self.profession_order.push_back(character_name.to_owned());
+        if self.profession_order.len() > MAX_PROFESSIONS {
+            if let Some(oldest) = self.profession_order.pop_front() {
+                self.professions.remove(&oldest);
+            }
+        }
+    }
+
+    /// Takes the indices of the lines changed in place since the last call.
+    pub fn take_changed(&mut self) -> BTreeSet<u64> {
+        std::mem::take(&mut self.changed)
+    }
+
+    /// The index after the last line in the buffer.
+    pub fn end(&self) -> u64 {
+        self.dropped + self.buffer.len() as u64
+    }
+
+    /// Indices of the lines logged between `from` and `to`, inclusive. Lines are assumed
+    /// to be in time order, which holds apart from repeats and bursts being kept at the
+    /// time of their first line.
+    pub fn time_range(
+        &self,
+        from: chrono::DateTime<Local>,
+        to: chrono::DateTime<Local>,
+    ) -> Range<u64> {
+        let start = self.buffer.partition_point(|line| line.time < from);
+        let end = self
+            .buffer
+            .partition_point(|line| line.time <= to)
+            .max(start);
+        self.dropped + start as u64..self.dropped + end as u64
+    }
+
     pub fn process_message(&mut self, message: &SquadMessageOwned) {
         self.insert_message(self.chat_message_to_line(message))
     }
 
     pub fn insert_squad_update(&mut self, line: String) {
-        let mut log_line = LogLine::new();
-        log_line.log_type = LogType::SquadUpdate;
-        log_line.parts.push(LogPart::new_current_time());
-        log_line
-            .parts
-            .push(LogPart::new_no_color(&format!("[Update] {}", line)));
-        self.insert_message(log_line)
+        self.insert_message(Self::squad_update_line(&line))
+    }
+
+    /// Inserts the line following a ready check, returning its index so it can be
+    /// replaced as the check goes on.
+    pub fn insert_ready_check(&mut self, parts: &mut Vec<LogPart>) -> u64 {
+        let mut line = Self::update_line(LogType::SquadUpdate, parts);
+        line.ready_check = true;
+        self.insert_message(line);
+        self.end() - 1
+    }
+
+    /// Replaces the summary of the ready check line at `index`, returning whether it is
+    /// still in the buffer.
+    pub fn replace_ready_check(&mut self, index: u64, parts: &mut Vec<LogPart>) -> bool {
+        let Some(line) = index
+            .checked_sub(self.dropped)
+            .and_then(|index| self.buffer.get_mut(index as usize))
+            .filter(|line| line.log_type == LogType::SquadUpdate && line.ready_check)
+        else {
+            return false;
+        };
+        line.parts.truncate(2);
+        line.parts.append(parts);
+        // the new text can change which filters the line passes
+        self.changed.insert(index);
+        true
+    }
+
+    fn squad_update_line(text: &str) -> LogLine {
+        let mut line = LogLine::new();
+        line.log_type = LogType::SquadUpdate;
+        line.parts.push(LogPart::new_current_time());
+        line.parts
+            .push(LogPart::new_no_color(&format!("[Update] {}", text)));
+        line
     }
 
     pub fn insert_squad_update_parts(&mut self, parts: &mut Vec<LogPart>) {
-        let mut log_line = LogLine::new();
-        log_line.log_type = LogType::SquadUpdate;
-        log_line.parts.push(LogPart::new_current_time());
-        log_line.parts.push(LogPart::new_no_color("[Update] "));
-        log_line.parts.append(parts);
-        self.insert_message(log_line)
+        self.insert_message(Self::update_line(LogType::SquadUpdate, parts))
     }
 
     pub fn insert_combat_update_parts(&mut self, parts: &mut Vec<LogPart>) {
+        self.insert_message(Self::update_line(LogType::Combat, parts))
+    }
+
+    /// Inserts an update that can be part of a burst, grouping it with the previous update
+    /// of the same kind if that came within [`BURST_WINDOW`].
+    pub fn insert_burst_parts(&mut self, burst: Burst, parts: &mut Vec<LogPart>) {
+        let line = Self::update_line(burst.log_type(), parts);
+        if self.group_bursts {
+            if let Some(group) = self.burst_group(burst, line.time) {
+                if group.details.is_empty() {
+                    // turn the first update into the summary, keeping it as a detail
+                    let summary = Self::update_line(burst.log_type(), &mut Vec::new());
+                    let first = std::mem::replace(group, summary);
+                    group.time = first.time;
+                    group.burst = Some(burst);
+                    group.details.push(first);
+                }
+                group.details.push(line);
+                group.parts[0] = LogPart::new_time(group.time);
+                group.summarise_burst(burst);
+                // grouped lines can change which filters the summary passes
+                self.changed.insert(self.bursts[&burst]);
+                return;
+            }
+        }
+        let index = self.end();
+        self.bursts.insert(burst, index);
+        self.insert_message(line);
+    }
+
+    /// Returns the line the last update of `burst` went into, if it came within the window.
+    fn burst_group(&mut self, burst: Burst, time: chrono::DateTime<Local>) -> Option<&mut LogLine> {
+        let index = *self.bursts.get(&burst)?;
+        let line = self
+            .buffer
+            .get_mut(index.checked_sub(self.dropped)? as usize)?;
+        let last = line.details.last().map_or(line.time, |last| last.time);
+        (time - last <= BURST_WINDOW).then_some(line)
+    }
+
+    fn update_line(log_type: LogType, parts: &mut Vec<LogPart>) -> LogLine {
         let mut log_line = LogLine::new();
-        log_line.log_type = LogType::Combat;
+        log_line.log_type = log_type;
         log_line.parts.push(LogPart::new_current_time());
-        log_line.parts.push(LogPart::new_no_color("[Combat] "));
+        log_line.parts.push(LogPart::new_no_color(match log_type {
+            LogType::Combat => "[Combat] ",
+            _ => "[Update] ",
+        }));
         log_line.parts.append(parts);
-        self.insert_message(log_line)
+        log_line
     }
 
     pub fn insert_message(&mut self, message: LogLine) {
+        if let Some(chat_message) = &message.message {
+            if self.collapse_repeats {
+                if let Some(line) = self.repeated_line(chat_message, message.time) {
+                    line.repeats += 1;
+                    line.last_repeat = Some(message.time);
+                    // the line passes the same filters as before, so this isn't a new generation
+                    return;
+                }
+            }
+            let index = self.end();
+            self.last_messages
+                .insert(chat_message.account_name.clone(), index);
+        }
         self.buffer.push_back(message);
-        if self.buffer.len() > self.buffer_max_size {
+        while self.buffer.len() > self.buffer_max_size {
             self.buffer.pop_front();
+            self.dropped += 1;
         }
+        if self
+            .changed
+            .first()
+            .is_some_and(|index| *index < self.dropped)
+        {
+            self.changed = self.changed.split_off(&self.dropped);
+        }
+    }
+
+    /// Returns the line `message` repeats: the account's last message, if it was in the
+    /// same channel with the same text, and within the repeat window.
+    fn repeated_line(
+        &mut self,
+        message: &SquadMessageOwned,
+        time: chrono::DateTime<Local>,
+    ) -> Option<&mut LogLine> {
+        let index = *self.last_messages.get(&message.account_name)?;
+        let line = self
+            .buffer
+            .get_mut(index.checked_sub(self.dropped)? as usize)?;
+        let previous = line.message.as_ref()?;
+        let repeat = previous.channel_type == message.channel_type
+            && previous.subgroup == message.subgroup
+            && repeats::within_window(line.last_seen(), time, self.repeat_window)
+            && repeats::normalize(&previous.text) == repeats::normalize(&message.text);
+        repeat.then_some(line)
+    }
+
+    /// Removes the lines loaded from the database, keeping those from this session.
+    pub fn clear_history(&mut self) {
+        self.buffer.retain(|line| !line.history);
+        self.last_messages.clear();
+        self.bursts.clear();
+        self.changed.clear();
+        self.generation += 1;
+    }
+
+    /// Inserts messages from a previous session before everything in the buffer.
+    /// `messages` are expected newest first, and are dropped once the buffer is full.
+    pub fn backfill(&mut self, messages: &[StoredMessage]) {
+        for message in messages {
+            if self.buffer.len() >= self.buffer_max_size {
+                break;
+            }
+            let mut line = self.chat_message_to_line(&message.to_squad_message());
+            line.history = true;
+            self.buffer.push_front(line);
+        }
+        self.last_messages.clear();
+        self.bursts.clear();
+        self.changed.clear();
+        self.generation += 1;
+    }
+
+    /// Replaces the highlight rules and reapplies them to the chat messages in the buffer.
+    /// Only lines whose highlights change are rebuilt.
+    pub fn set_highlighter(&mut self, highlighter: Highlighter) {
+        let previous = std::mem::replace(&mut self.highlighter, highlighter);
+        self.rebuild_messages(Some(&previous));
+    }
+
+    /// Replaces the names used to label chat links, and relabels the links in the buffer.
+    pub fn set_chat_link_names(&mut self, chat_link_names: Arc<ChatLinkNames>) {
+        self.chat_link_names = chat_link_names;
+        self.rebuild_messages(None);
+    }
+
+    /// Rebuilds the chat message lines, or only those highlighted differently by
+    /// `previous` when the highlighter was replaced.
+    fn rebuild_messages(&mut self, previous: Option<&Highlighter>) {
+        let mut rebuilt = false;
+        for index in 0..self.buffer.len() {
+            let Some(message) = &self.buffer[index].message else {
+                continue;
+            };
+            if previous
+                .is_some_and(|previous| previous.spans(message) == self.highlighter.spans(message))
+            {
+                continue;
+            }
+            let mut line = self.chat_message_to_line(message);
+            line.history = self.buffer[index].history;
+            line.repeats = self.buffer[index].repeats;
+            line.last_repeat = self.buffer[index].last_repeat;
+            self.buffer[index] = line;
+            rebuilt = true;
+        }
+        if rebuilt {
+            self.generation += 1;
+        }
+    }
+
+    /// Removes messages from `account_name` and updates that mention it, and forgets the
+    /// professions of its characters.
+    pub fn purge_account(&mut self, account_name: &str) {
+        for line in &mut self.buffer {
+            if let Some(burst) = line.burst {
+                line.details
+                    .retain(|detail| !detail.mentions_account(account_name));
+                line.summarise_burst(burst);
+            }
+        }
+        self.buffer.retain(|line| {
+            !line.mentions_account(account_name)
+                && !(line.burst.is_some() && line.details.is_empty())
+        });
+        self.professions
+            .retain(|_, (character_account, _)| character_account != account_name);
+        self.profession_order
+            .retain(|character_name| self.professions.contains_key(character_name));
+        self.last_messages.clear();
+        self.bursts.clear();
+        self.changed.clear();
+        self.generation += 1;
     }
 
     fn chat_message_to_line(&self, message: &SquadMessageOwned) -> LogLine {
@@ -290,27 +870,30 @@ impl LogBuffer {
             ChannelType::Reserved => LogType::Generic,
             ChannelType::Invalid => LogType::Generic,
         };
-        let text_color = match message.channel_type {
-            ChannelType::Party => Some(self.colors.party_chat),
+        let text_role = match message.channel_type {
+            ChannelType::Party => Some(ColorRole::PartyChat),
             ChannelType::Squad => Some(if message.subgroup == 255 {
-                self.colors.squad_chat
+                ColorRole::SquadChat
             } else {
-                self.colors.party_chat
+                ColorRole::PartyChat
             }),
             ChannelType::Reserved => None,
             ChannelType::Invalid => None,
         };
-        let user_color = match message.channel_type {
-            ChannelType::Party => Some(self.colors.party_user),
+        let user_role = match message.channel_type {
+            ChannelType::Party => Some(ColorRole::PartyUser),
             ChannelType::Squad => Some(if message.subgroup == 255 {
-                self.colors.squad_user
+                ColorRole::SquadUser
             } else {
-                self.colors.party_user
+                ColorRole::PartyUser
             }),
             ChannelType::Reserved => None,
             ChannelType::Invalid => None,
         };
 
+        if let Some(timestamp) = message.timestamp {
+            line.time = timestamp.with_timezone(&Local);
+        }
         line.parts
             .push(LogPart::new_time(message.timestamp.unwrap_or_default()));
         line.parts.push(LogPart::new(
@@ -321,30 +904,102 @@ impl LogBuffer {
         ));
         if message.channel_type == ChannelType::Squad {
             if message.subgroup != 255 {
-                line.parts.push(LogPart::new(
-                    &format!("[{}]", message.subgroup + 1),
-                    None,
-                    text_color,
-                    None,
-                ));
+                line.parts.push(
+                    LogPart::new_no_color(&format!("[{}]", message.subgroup + 1))
+                        .with_role(text_role),
+                );
             }
             if message.flags.contains(SquadMessageFlags::IS_BROADCAST) {
                 line.parts
-                    .push(LogPart::new("[BROADCAST]", None, text_color, None));
+                    .push(LogPart::new_no_color("[BROADCAST]").with_role(text_role));
             }
         }
-        line.parts.push(LogPart::new(
-            &format!(" {}", message.character_name),
-            Some(&message.account_name),
-            user_color,
-            Some(&message.character_name),
-        ));
-        line.parts.push(LogPart::new(
-            &format!(": {}", message.text),
-            None,
-            text_color,
-            Some(&message.text),
-        ));
+        line.parts.push(
+            LogPart::new(
+                &format!(" {}", message.character_name),
+                Some(&message.account_name),
+                None,
+                Some(&message.character_name),
+            )
+            .with_role(user_role)
+            .with_profession(
+                self.professions
+                    .get(&message.character_name)
+                    .map(|(_, profession)| *profession),
+            ),
+        );
+        line.parts
+            .push(LogPart::new_no_color(": ").with_role(text_role));
+        let mut end = 0;
+        for (range, segment) in self.message_segments(message) {
+            if end < range.start {
+                line.parts.push(
+                    LogPart::new(
+                        &message.text[end..range.start],
+                        None,
+                        None,
+                        Some(&message.text),
+                    )
+                    .with_role(text_role),
+                );
+            }
+            let mut part = match segment {
+                Segment::Highlight(highlight) => {
+                    LogPart::new_no_color(&message.text[range.clone()])
+                        .with_highlight(Some(highlight))
+                }
+                Segment::ChatLink(Some(link)) => LogPart::new_chat_link(
+                    &self.chat_link_names.label(&link),
+                    &message.text[range.clone()],
+                    &self.chat_link_names.details(&link),
+                ),
+                Segment::ChatLink(None) => LogPart::new_chat_link(
+                    &message.text[range.clone()],
+                    &message.text[range.clone()],
+                    "Unrecognised chat link",
+                ),
+            }
+            .with_role(text_role);
+            part.clipboard = Some(message.text.clone());
+            line.parts.push(part);
+            end = range.end;
+        }
+        if end < message.text.len() {
+            line.parts.push(
+                LogPart::new(&message.text[end..], None, None, Some(&message.text))
+                    .with_role(text_role),
+            );
+        }
+        line.message = Some(message.clone());
         line
     }
+
+    /// Splits out the chat links and highlighted spans of a message, in order.
+    /// Chat links take priority over highlights that overlap them.
+    fn message_segments(&self, message: &SquadMessageOwned) -> Vec<(Range<usize>, Segment)> {
+        let mut segments: Vec<(Range<usize>, Segment)> = chatlink::find_chat_links(&message.text)
+            .into_iter()
+            .map(|(range, link)| (range, Segment::ChatLink(link)))
+            .collect();
+        let links: Vec<Range<usize>> = segments.iter().map(|(range, _)| range.clone()).collect();
+        segments.extend(
+            self.highlighter
+                .spans(message)
+                .into_iter()
+                .filter(|(range, _)| {
+                    !links
+                        .iter()
+                        .any(|link| range.start < link.end && link.start < range.end)
+                })
+                .map(|(range, highlight)| (range, Segment::Highlight(highlight))),
+        );
+        segments.sort_by_key(|(range, _)| range.start);
+        segments
+    }
+}
+
+/// A part of a chat message that is shown differently from the rest of the text.
+enum Segment {
+    Highlight(Highlight),
+    ChatLink(Option<chatlink::ChatLink>),
 }
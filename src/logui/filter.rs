@@ -0,0 +1,106 @@
+use regex::{Regex, RegexBuilder};
+
+#[derive(Debug)]
+enum Matcher {
+    Contains(String),
+    ContainsIgnoreCase(String),
+    Regex(Regex),
+    /// The pattern failed to compile, so everything matches until it is fixed.
+    Invalid(String),
+}
+
+/// A text filter compiled from user input. Recompiles only when the input or mode changes,
+/// so it can be updated every frame.
+#[derive(Debug)]
+pub struct TextFilter {
+    text: String,
+    case_sensitive: bool,
+    regex: bool,
+    matcher: Matcher,
+}
+
+impl TextFilter {
+    pub fn new() -> Self {
+        Self {
+            text: String::new(),
+            case_sensitive: false,
+            regex: false,
+            matcher: Matcher::ContainsIgnoreCase(String::new()),
+        }
+    }
+
+    pub fn update(&mut self, text: &str, case_sensitive: bool, regex: bool) {
+        if self.text == text && self.case_sensitive == case_sensitive && self.regex == regex {
+            return;
+        }
+        self.text = text.to_owned();
+        self.case_sensitive = case_sensitive;
+        self.regex = regex;
+        self.matcher = if regex {
+            match RegexBuilder::new(text)
+                .case_insensitive(!case_sensitive)
+                .build()
+            {
+                Ok(regex) => Matcher::Regex(regex),
+                Err(err) => Matcher::Invalid(err.to_string()),
+            }
+        } else if case_sensitive {
+            Matcher::Contains(text.to_owned())
+        } else {
+            Matcher::ContainsIgnoreCase(text.to_lowercase())
+        };
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// Returns the compile error if the regex is invalid.
+    pub fn error(&self) -> Option<&str> {
+        match &self.matcher {
+            Matcher::Invalid(err) => Some(err),
+            _ => None,
+        }
+    }
+
+    pub fn matches(&self, haystack: &str) -> bool {
+        match &self.matcher {
+            Matcher::Contains(text) => haystack.contains(text.as_str()),
+            Matcher::ContainsIgnoreCase(text) => haystack.to_lowercase().contains(text.as_str()),
+            Matcher::Regex(regex) => regex.is_match(haystack),
+            Matcher::Invalid(_) => true,
+        }
+    }
+}
+
+impl Default for TextFilter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_filter() {
+        let mut filter = TextFilter::new();
+        filter.update("commander", false, false);
+        assert!(filter.matches("Commander Foo"));
+        assert!(!filter.matches("Lieutenant"));
+
+        filter.update("commander", true, false);
+        assert!(!filter.matches("Commander Foo"));
+        assert!(filter.matches("the commander"));
+
+        filter.update(r"^\[squad\]", false, true);
+        assert!(filter.matches("[Squad] stack"));
+        assert!(!filter.matches("stack [Squad]"));
+        assert!(filter.error().is_none());
+
+        filter.update("(unclosed", false, true);
+        assert!(filter.error().is_some());
+        assert!(filter.matches("anything"));
+    }
+}
@@ -0,0 +1,241 @@
+use std::ops::Range;
+
+use arcdps::extras::message::{ChannelType, SquadMessageOwned};
+use regex::{Regex, RegexBuilder};
+
+use super::settings::{HighlightChannel, HighlightRule};
+
+/// How a highlighted span of text is drawn.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Highlight {
+    pub color: [f32; 4],
+    pub bold: bool,
+    pub background: bool,
+}
+
+#[derive(Debug)]
+struct CompiledRule {
+    pattern: Regex,
+    channel: HighlightChannel,
+    account: String,
+    /// Account whose messages the rule skips, lowercased, or empty to skip none.
+    except_account: String,
+    highlight: Highlight,
+}
+
+impl CompiledRule {
+    fn new(rule: &HighlightRule) -> Option<Self> {
+        if !rule.enabled || rule.pattern.is_empty() {
+            return None;
+        }
+        let pattern = compile(rule).ok()?;
+        Some(Self::from_pattern(rule, pattern))
+    }
+
+    fn from_pattern(rule: &HighlightRule, pattern: Regex) -> Self {
+        Self {
+            pattern,
+            channel: rule.channel,
+            account: rule.account.trim().to_lowercase(),
+            except_account: String::new(),
+            highlight: Highlight {
+                color: rule.color,
+                bold: rule.bold,
+                background: rule.background,
+            },
+        }
+    }
+
+    fn applies_to(&self, message: &SquadMessageOwned) -> bool {
+        let channel = match self.channel {
+            HighlightChannel::Any => true,
+            HighlightChannel::Squad => message.channel_type == ChannelType::Squad,
+            HighlightChannel::Party => message.channel_type == ChannelType::Party,
+        };
+        let account = message.account_name.to_lowercase();
+        channel
+            && (self.account.is_empty() || account == self.account)
+            && (self.except_account.is_empty() || account != self.except_account)
+    }
+}
+
+/// Highlight rules compiled from the settings, in priority order.
+#[derive(Debug, Default)]
+pub struct Highlighter {
+    rules: Vec<CompiledRule>,
+    /// Compile errors, by index into the rules the highlighter was built from.
+    errors: Vec<Option<String>>,
+}
+
+impl Highlighter {
+    pub fn new(rules: &[HighlightRule]) -> Self {
+        let mut highlighter = Self::default();
+        for rule in rules {
+            let error = match compile(rule) {
+                Ok(pattern) if rule.enabled && !rule.pattern.is_empty() => {
+                    highlighter
+                        .rules
+                        .push(CompiledRule::from_pattern(rule, pattern));
+                    None
+                }
+                Ok(_) => None,
+                Err(err) => Some(err.to_string()),
+            };
+            highlighter.errors.push(error);
+        }
+        highlighter
+    }
+
+    /// Adds a rule that takes priority over all others, skipping messages from
+    /// `except_account`. Compile errors are ignored.
+    pub fn prepend(&mut self, rule: &HighlightRule, except_account: &str) {
+        if let Some(mut compiled) = CompiledRule::new(rule) {
+            compiled.except_account = except_account.trim().to_lowercase();
+            self.rules.insert(0, compiled);
+        }
+    }
+
+    pub fn error(&self, index: usize) -> Option<&str> {
+        self.errors.get(index)?.as_deref()
+    }
+
+    /// Returns the non-overlapping byte ranges of the message text to highlight, in order.
+    /// Where matches overlap, the earlier rule wins.
+    pub fn spans(&self, message: &SquadMessageOwned) -> Vec<(Range<usize>, Highlight)> {
+        let mut spans: Vec<(Range<usize>, Highlight)> = Vec::new();
+        for rule in self.rules.iter().filter(|rule| rule.applies_to(message)) {
+            for found in rule.pattern.find_iter(&message.text) {
+                let range = found.range();
+                if range.is_empty()
+                    || spans
+                        .iter()
+                        .any(|(span, _)| range.start < span.end && span.start < range.end)
+                {
+                    continue;
+                }
+                spans.push((range, rule.highlight));
+            }
+        }
+        spans.sort_by_key(|(range, _)| range.start);
+        spans
+    }
+}
+
+fn compile(rule: &HighlightRule) -> Result<Regex, regex::Error> {
+    let pattern = if rule.regex {
+        rule.pattern.clone()
+    } else {
+        word_pattern(&rule.pattern)
+    };
+    RegexBuilder::new(&pattern)
+        .case_insensitive(!rule.case_sensitive)
+        .build()
+}
+
+/// Escapes `word` so it only matches whole words, while still allowing words that
+/// start or end with punctuation like "@me" or "pull!".
+pub fn word_pattern(word: &str) -> String {
+    let word = word.trim();
+    let start = if word.starts_with(is_word_char) {
+        r"\b"
+    } else {
+        ""
+    };
+    let end = if word.ends_with(is_word_char) {
+        r"\b"
+    } else {
+        ""
+    };
+    format!("{}{}{}", start, regex::escape(word), end)
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arcdps::extras::message::SquadMessageFlags;
+
+    fn message(channel_type: ChannelType, account_name: &str, text: &str) -> SquadMessageOwned {
+        SquadMessageOwned {
+            channel_id: 0,
+            channel_type,
+            subgroup: 255,
+            flags: SquadMessageFlags::empty(),
+            timestamp: None,
+            account_name: account_name.to_owned(),
+            character_name: "Foo".to_owned(),
+            text: text.to_owned(),
+        }
+    }
+
+    fn rule(pattern: &str) -> HighlightRule {
+        HighlightRule {
+            pattern: pattern.to_owned(),
+            ..HighlightRule::new()
+        }
+    }
+
+    #[test]
+    fn test_spans() {
+        let highlighter = Highlighter::new(&[
+            rule("pull"),
+            HighlightRule {
+                regex: true,
+                bold: true,
+                ..rule(r"pull in \d+")
+            },
+            HighlightRule {
+                channel: HighlightChannel::Party,
+                ..rule("stack")
+            },
+            HighlightRule {
+                account: "Baz.5678".to_owned(),
+                ..rule("go")
+            },
+        ]);
+
+        let text = "Pull in 5, pulling now, stack and go";
+        let spans = highlighter.spans(&message(ChannelType::Squad, "Foo.1234", text));
+        // whole words only, and the earlier rule wins the overlap
+        assert_eq!(spans.len(), 1);
+        assert_eq!(&text[spans[0].0.clone()], "Pull");
+        assert!(!spans[0].1.bold);
+
+        let spans = highlighter.spans(&message(ChannelType::Party, "baz.5678", text));
+        let matched: Vec<&str> = spans
+            .iter()
+            .map(|(range, _)| &text[range.clone()])
+            .collect();
+        assert_eq!(matched, ["Pull", "stack", "go"]);
+
+        let mut highlighter = Highlighter::new(&[]);
+        highlighter.prepend(&rule("Foo"), "Foo.1234");
+        assert!(highlighter
+            .spans(&message(ChannelType::Squad, "foo.1234", "Foo here"))
+            .is_empty());
+        assert_eq!(
+            highlighter
+                .spans(&message(ChannelType::Squad, "Bar.5678", "Foo here"))
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let highlighter = Highlighter::new(&[
+            HighlightRule {
+                regex: true,
+                ..rule("(unclosed")
+            },
+            rule("@me"),
+        ]);
+        assert!(highlighter.error(0).is_some());
+        assert!(highlighter.error(1).is_none());
+        let spans = highlighter.spans(&message(ChannelType::Squad, "Foo.1234", "ping @me"));
+        assert_eq!(spans.len(), 1);
+    }
+}
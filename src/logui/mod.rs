@@ -1,27 +1,71 @@
 use std::sync::{Arc, Mutex};
 
+use chrono::{DateTime, Local};
+
 use crate::db::ChatDatabase;
 
-use self::{buffer::LogBuffer, settings::ChatLogSettings};
+use self::{
+    buffer::LogBuffer,
+    filter::TextFilter,
+    highlight::Highlighter,
+    query::FilterQuery,
+    settings::{ChatLogSettings, HighlightRule},
+    visible::VisibleLines,
+};
 use windows::System::VirtualKey;
 
 pub mod buffer;
+mod filter;
+pub mod highlight;
+pub mod query;
 mod settings;
 mod ui;
+mod visible;
 
 #[derive(Debug)]
 struct LocalProps {
     pub account_filter: String,
-    pub text_filter: String,
+    pub active_view: usize,
     pub account_width: f32,
+    pub account_matcher: TextFilter,
+    pub text_matcher: TextFilter,
+    pub query: FilterQuery,
+    pub purge_request: Option<String>,
+    pub purge_confirm: Option<String>,
+    pub visible: VisibleLines,
+    /// The `after:` and `before:` times of the query when lines were last filtered.
+    pub time_bounds: [Option<DateTime<Local>>; 2],
+    pub autoscroll_paused: bool,
+    pub jump_to_bottom: bool,
+    /// Frame the window was last rendered in, to tell when it was hidden.
+    pub last_frame: Option<i32>,
+    /// Buffer index after the last line when the window was last rendered.
+    pub seen_end: u64,
+    /// Buffer index of the first line that arrived while scrolled up.
+    pub unread_from: Option<u64>,
+    /// Buffer index of the first line that arrived while the window was hidden.
+    pub unread_marker: Option<u64>,
 }
 
 impl LocalProps {
     pub fn new() -> Self {
         Self {
             account_filter: String::new(),
-            text_filter: String::new(),
+            active_view: 0,
             account_width: 100.0,
+            account_matcher: TextFilter::new(),
+            text_matcher: TextFilter::new(),
+            query: FilterQuery::default(),
+            purge_request: None,
+            purge_confirm: None,
+            visible: VisibleLines::default(),
+            time_bounds: [None, None],
+            autoscroll_paused: false,
+            jump_to_bottom: false,
+            last_frame: None,
+            seen_end: 0,
+            unread_from: None,
+            unread_marker: None,
         }
     }
 }
@@ -30,6 +74,12 @@ pub struct LogUi {
     pub settings: ChatLogSettings,
     pub buffer: LogBuffer,
     pub chat_database: Option<Arc<Mutex<ChatDatabase>>>,
+    /// Account the user confirmed should be purged, handled by the plugin after rendering.
+    pub purge_account: Option<String>,
+    /// Highlights mentions of the user, ahead of the configured highlight rules.
+    mention_rule: Option<HighlightRule>,
+    /// The user's account, whose own messages aren't highlighted as mentions.
+    mention_account: String,
     ui_props: LocalProps,
 }
 
@@ -37,16 +87,50 @@ impl LogUi {
     pub const DEFAULT_HOTKEY: u32 = VirtualKey::J.0 as u32;
 
     pub fn new() -> Self {
-        Self {
+        let mut log_ui = Self {
             settings: ChatLogSettings::new(),
             buffer: LogBuffer::new(),
             chat_database: None,
+            purge_account: None,
+            mention_rule: None,
+            mention_account: String::new(),
             ui_props: LocalProps::new(),
-        }
+        };
+        log_ui.update_settings();
+        log_ui
     }
 
     pub fn update_settings(&mut self) {
-        self.buffer.colors = self.settings.color_settings;
+        self.settings.migrate_views();
+        self.ui_props.active_view = self.ui_props.active_view.min(self.settings.views.len() - 1);
+        self.update_highlights();
+    }
+
+    pub fn update_highlights(&mut self) {
+        let mut highlighter = Highlighter::new(&self.settings.highlight_rules);
+        if let Some(rule) = &self.mention_rule {
+            highlighter.prepend(rule, &self.mention_account);
+        }
+        self.buffer.set_highlighter(highlighter);
+    }
+
+    /// Sets the regex matching mentions of the user, or `None` to stop highlighting them.
+    /// Messages from `self_account_name` are never highlighted as mentions.
+    pub fn set_mention_highlight(
+        &mut self,
+        pattern: Option<&str>,
+        color: [f32; 4],
+        self_account_name: &str,
+    ) {
+        self.mention_account = self_account_name.to_owned();
+        self.mention_rule = pattern.map(|pattern| HighlightRule {
+            pattern: pattern.to_owned(),
+            regex: true,
+            color,
+            background: true,
+            ..HighlightRule::new()
+        });
+        self.update_highlights();
     }
 }
 
@@ -0,0 +1,323 @@
+use arcdps::extras::message::ChannelType;
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+
+use super::buffer::{LogLine, LogType};
+
+/// A filter typed into the squad log or history search, e.g.
+/// `from:Foo.1234 channel:party sub:3 "stack"`.
+///
+/// Supported keys:
+/// - `from:` account or character name
+/// - `account:` and `char:` account or character name only
+/// - `channel:` `squad` or `party`
+/// - `sub:` subgroup number
+/// - `type:` `squad`, `party`, `update`, `combat` or `other`
+/// - `after:` and `before:` `HH:MM` today, `YYYY-MM-DD`, `YYYY-MM-DD HH:MM` (quoted),
+///   or a relative time like `30m`, `2h` or `1d`
+///
+/// Everything else, including quoted text and unknown keys, is matched against the message text.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FilterQuery {
+    pub text: String,
+    pub from: Option<String>,
+    pub account: Option<String>,
+    pub character: Option<String>,
+    pub channel: Option<ChannelType>,
+    /// Subgroup as shown in game, starting from 1.
+    pub subgroup: Option<u8>,
+    pub log_type: Option<LogType>,
+    pub after: Option<DateTime<Local>>,
+    pub before: Option<DateTime<Local>>,
+    pub errors: Vec<String>,
+}
+
+struct Token {
+    text: String,
+    /// Tokens starting with a quote are always text, even if they contain a colon.
+    literal: bool,
+}
+
+impl FilterQuery {
+    pub fn parse(input: &str, now: DateTime<Local>) -> Self {
+        let mut query = Self::default();
+        let mut text = Vec::new();
+        for token in tokenize(input) {
+            let key_value = (!token.literal)
+                .then(|| token.text.split_once(':'))
+                .flatten()
+                .map(|(key, value)| (key.to_lowercase(), value));
+            match key_value {
+                Some((key, value)) if is_key(&key) => {
+                    if !value.is_empty() {
+                        query.apply(&key, value, now);
+                    }
+                }
+                _ => text.push(token.text),
+            }
+        }
+        query.text = text.join(" ");
+        query
+    }
+
+    fn apply(&mut self, key: &str, value: &str, now: DateTime<Local>) {
+        match key {
+            "from" => self.from = Some(value.to_owned()),
+            "account" => self.account = Some(value.to_owned()),
+            "char" | "character" => self.character = Some(value.to_owned()),
+            "channel" => match value.to_lowercase().as_str() {
+                "squad" => self.channel = Some(ChannelType::Squad),
+                "party" => self.channel = Some(ChannelType::Party),
+                _ => self.errors.push(format!(
+                    "unknown channel \"{}\", expected squad or party",
+                    value
+                )),
+            },
+            "sub" | "subgroup" => match value.parse::<u8>() {
+                Ok(subgroup) if (1..=15).contains(&subgroup) => self.subgroup = Some(subgroup),
+                _ => self
+                    .errors
+                    .push(format!("invalid subgroup \"{}\", expected 1 to 15", value)),
+            },
+            "type" => match value.to_lowercase().as_str() {
+                "squad" => self.log_type = Some(LogType::SquadMessage),
+                "party" => self.log_type = Some(LogType::PartyMessage),
+                "update" | "updates" => self.log_type = Some(LogType::SquadUpdate),
+                "combat" => self.log_type = Some(LogType::Combat),
+                "other" | "others" => self.log_type = Some(LogType::Generic),
+                _ => self.errors.push(format!(
+                    "unknown type \"{}\", expected squad, party, update, combat or other",
+                    value
+                )),
+            },
+            "after" | "before" => match parse_time(value, now) {
+                Some(time) if key == "after" => self.after = Some(time),
+                Some(time) => self.before = Some(time),
+                None => self.errors.push(format!("invalid time \"{}\"", value)),
+            },
+            _ => {}
+        }
+    }
+
+    /// Returns a description of the first invalid term, if any.
+    pub fn error(&self) -> Option<&str> {
+        self.errors.first().map(String::as_str)
+    }
+
+    /// Checks everything except the text, which is matched separately so it can use
+    /// the case and regex modes of the text filter.
+    pub fn matches_line(&self, line: &LogLine) -> bool {
+        if self
+            .log_type
+            .is_some_and(|log_type| log_type != line.log_type)
+        {
+            return false;
+        }
+        if self.after.is_some_and(|after| line.time < after)
+            || self.before.is_some_and(|before| line.time > before)
+        {
+            return false;
+        }
+        if self.channel.is_some() || self.subgroup.is_some() {
+            let Some(message) = &line.message else {
+                return false;
+            };
+            if self
+                .channel
+                .as_ref()
+                .is_some_and(|channel| *channel != message.channel_type)
+                || self
+                    .subgroup
+                    .is_some_and(|subgroup| message.subgroup != subgroup - 1)
+            {
+                return false;
+            }
+        }
+        let accounts = || {
+            line.message
+                .iter()
+                .map(|message| message.account_name.as_str())
+                .chain(line.parts.iter().filter_map(|part| part.hover.as_deref()))
+        };
+        // squad updates name players with the character as the text and the account as the hover
+        let characters = || {
+            line.message
+                .iter()
+                .map(|message| message.character_name.as_str())
+                .chain(
+                    line.parts
+                        .iter()
+                        .filter(|part| part.hover.is_some())
+                        .map(|part| part.text.trim()),
+                )
+        };
+        if let Some(account) = &self.account {
+            if !accounts().any(|name| contains_ignore_case(name, account)) {
+                return false;
+            }
+        }
+        if let Some(character) = &self.character {
+            if !characters().any(|name| contains_ignore_case(name, character)) {
+                return false;
+            }
+        }
+        if let Some(from) = &self.from {
+            if !accounts()
+                .chain(characters())
+                .any(|name| contains_ignore_case(name, from))
+            {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+fn is_key(key: &str) -> bool {
+    matches!(
+        key,
+        "from"
+            | "account"
+            | "char"
+            | "character"
+            | "channel"
+            | "sub"
+            | "subgroup"
+            | "type"
+            | "after"
+            | "before"
+    )
+}
+
+/// Splits on whitespace, keeping double quoted sections together and dropping the quotes.
+fn tokenize(input: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let Some(&first) = chars.peek() else {
+            break;
+        };
+        let mut token = Token {
+            text: String::new(),
+            literal: first == '"',
+        };
+        let mut in_quotes = false;
+        while let Some(&c) = chars.peek() {
+            if c == '"' {
+                in_quotes = !in_quotes;
+            } else if c.is_whitespace() && !in_quotes {
+                break;
+            } else {
+                token.text.push(c);
+            }
+            chars.next();
+        }
+        tokens.push(token);
+    }
+    tokens
+}
+
+fn parse_time(value: &str, now: DateTime<Local>) -> Option<DateTime<Local>> {
+    if let Some(unit) = value.chars().last() {
+        if let Ok(amount) = value[..value.len() - unit.len_utf8()].parse::<i64>() {
+            let duration = match unit {
+                'm' => Duration::try_minutes(amount),
+                'h' => Duration::try_hours(amount),
+                'd' => Duration::try_days(amount),
+                _ => None,
+            };
+            if let Some(duration) = duration {
+                return now.checked_sub_signed(duration);
+            }
+        }
+    }
+    let naive = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .ok()
+                .map(|date| date.and_time(NaiveTime::MIN))
+        })
+        .or_else(|| {
+            NaiveTime::parse_from_str(value, "%H:%M")
+                .ok()
+                .map(|time| now.date_naive().and_time(time))
+        })?;
+    Local.from_local_datetime(&naive).earliest()
+}
+
+fn contains_ignore_case(haystack: &str, needle: &str) -> bool {
+    haystack.to_lowercase().contains(&needle.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn now() -> DateTime<Local> {
+        Local.with_ymd_and_hms(2024, 3, 10, 15, 30, 0).unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        let query = FilterQuery::parse(r#"from:Foo.1234 channel:party sub:3 "stack""#, now());
+        assert_eq!(
+            query,
+            FilterQuery {
+                text: "stack".to_owned(),
+                from: Some("Foo.1234".to_owned()),
+                channel: Some(ChannelType::Party),
+                subgroup: Some(3),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_text() {
+        let query = FilterQuery::parse(r#"stack  on "from:tag" http://x"#, now());
+        assert_eq!(query.text, "stack on from:tag http://x");
+        assert!(query.from.is_none());
+        assert!(query.errors.is_empty());
+
+        // incomplete keys while typing are ignored
+        let query = FilterQuery::parse("from: char:", now());
+        assert_eq!(query, FilterQuery::default());
+    }
+
+    #[test]
+    fn test_parse_keys() {
+        let query = FilterQuery::parse(
+            r#"CHAR:"Foo Bar" account:Baz.5678 type:updates after:"2024-03-09 20:00" before:14:00"#,
+            now(),
+        );
+        assert_eq!(query.character.as_deref(), Some("Foo Bar"));
+        assert_eq!(query.account.as_deref(), Some("Baz.5678"));
+        assert_eq!(query.log_type, Some(LogType::SquadUpdate));
+        assert_eq!(
+            query.after,
+            Some(Local.with_ymd_and_hms(2024, 3, 9, 20, 0, 0).unwrap())
+        );
+        assert_eq!(
+            query.before,
+            Some(Local.with_ymd_and_hms(2024, 3, 10, 14, 0, 0).unwrap())
+        );
+
+        let query = FilterQuery::parse("after:2h before:2024-03-10", now());
+        assert_eq!(query.after, Some(now() - Duration::hours(2)));
+        assert_eq!(
+            query.before,
+            Some(Local.with_ymd_and_hms(2024, 3, 10, 0, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let query = FilterQuery::parse("channel:guild sub:16 type:x after:soon", now());
+        assert_eq!(query.errors.len(), 4);
+        assert!(query.channel.is_none());
+        assert!(query.subgroup.is_none());
+        assert!(query.error().unwrap().contains("guild"));
+    }
+}
@@ -1,13 +1,17 @@
 use std::path::PathBuf;
 
 use arc_util::settings::HasSettings;
+use arcdps::Profession;
+use chrono::{DateTime, Local, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 
+use crate::db::backup::BackupSettings;
+
 use super::LogUi;
 
 const DEFAULT_LOG_PATH: &str = "arcdps_chat_log.db";
 
-#[derive(Debug, Clone, Serialize, Deserialize, Copy)]
+#[derive(Debug, Clone, Serialize, Deserialize, Copy, PartialEq)]
 #[serde(default)]
 pub struct FilterSettings {
     pub squad_message: bool,
@@ -16,6 +20,8 @@ pub struct FilterSettings {
     pub combat_updates: bool,
     pub others: bool,
     pub hover_char_name_for_account_name: bool,
+    pub case_sensitive: bool,
+    pub regex: bool,
 }
 
 impl FilterSettings {
@@ -27,6 +33,8 @@ impl FilterSettings {
             combat_updates: true,
             others: true,
             hover_char_name_for_account_name: true,
+            case_sensitive: false,
+            regex: false,
         }
     }
 }
@@ -44,6 +52,9 @@ pub struct ColorSettings {
     pub squad_user: [f32; 4],
     pub party_chat: [f32; 4],
     pub party_user: [f32; 4],
+    /// Colour character names by their profession, where it is known, instead of by channel.
+    pub by_profession: bool,
+    pub professions: ProfessionColors,
 }
 
 impl ColorSettings {
@@ -54,6 +65,17 @@ impl ColorSettings {
             party_chat: [188.0 / 255.0, 222.0 / 255.0, 255.0 / 255.0, 1.0],
             squad_user: [192.0 / 255.0, 241.0 / 255.0, 97.0 / 255.0, 1.0],
             party_user: [68.0 / 255.0, 188.0 / 255.0, 255.0 / 255.0, 1.0],
+            by_profession: false,
+            professions: ProfessionColors::new(),
+        }
+    }
+
+    /// Colour for a character name of `profession`, if names are coloured by profession.
+    pub fn profession_color(&self, profession: Profession) -> Option<[f32; 4]> {
+        if self.by_profession {
+            self.professions.color(profession)
+        } else {
+            None
         }
     }
 }
@@ -64,13 +86,288 @@ impl Default for ColorSettings {
     }
 }
 
+/// Colours of character names by profession, defaulting to the colours the game uses.
+#[derive(Debug, Clone, Serialize, Deserialize, Copy)]
+#[serde(default)]
+pub struct ProfessionColors {
+    pub guardian: [f32; 4],
+    pub warrior: [f32; 4],
+    pub engineer: [f32; 4],
+    pub ranger: [f32; 4],
+    pub thief: [f32; 4],
+    pub elementalist: [f32; 4],
+    pub mesmer: [f32; 4],
+    pub necromancer: [f32; 4],
+    pub revenant: [f32; 4],
+}
+
+impl ProfessionColors {
+    pub fn new() -> Self {
+        #[allow(clippy::eq_op)]
+        Self {
+            guardian: [114.0 / 255.0, 193.0 / 255.0, 217.0 / 255.0, 1.0],
+            warrior: [255.0 / 255.0, 209.0 / 255.0, 102.0 / 255.0, 1.0],
+            engineer: [208.0 / 255.0, 156.0 / 255.0, 89.0 / 255.0, 1.0],
+            ranger: [140.0 / 255.0, 220.0 / 255.0, 130.0 / 255.0, 1.0],
+            thief: [192.0 / 255.0, 143.0 / 255.0, 149.0 / 255.0, 1.0],
+            elementalist: [246.0 / 255.0, 138.0 / 255.0, 135.0 / 255.0, 1.0],
+            mesmer: [182.0 / 255.0, 121.0 / 255.0, 213.0 / 255.0, 1.0],
+            necromancer: [82.0 / 255.0, 167.0 / 255.0, 111.0 / 255.0, 1.0],
+            revenant: [209.0 / 255.0, 110.0 / 255.0, 90.0 / 255.0, 1.0],
+        }
+    }
+
+    pub fn color(&self, profession: Profession) -> Option<[f32; 4]> {
+        self.iter()
+            .find(|(known, _)| *known == profession)
+            .map(|(_, color)| *color)
+    }
+
+    /// Every profession with its colour, in the order the game lists them.
+    pub fn iter(&self) -> impl Iterator<Item = (Profession, &[f32; 4])> {
+        [
+            (Profession::Guardian, &self.guardian),
+            (Profession::Revenant, &self.revenant),
+            (Profession::Warrior, &self.warrior),
+            (Profession::Engineer, &self.engineer),
+            (Profession::Ranger, &self.ranger),
+            (Profession::Thief, &self.thief),
+            (Profession::Elementalist, &self.elementalist),
+            (Profession::Mesmer, &self.mesmer),
+            (Profession::Necromancer, &self.necromancer),
+        ]
+        .into_iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Profession, &mut [f32; 4])> {
+        [
+            (Profession::Guardian, &mut self.guardian),
+            (Profession::Revenant, &mut self.revenant),
+            (Profession::Warrior, &mut self.warrior),
+            (Profession::Engineer, &mut self.engineer),
+            (Profession::Ranger, &mut self.ranger),
+            (Profession::Thief, &mut self.thief),
+            (Profession::Elementalist, &mut self.elementalist),
+            (Profession::Mesmer, &mut self.mesmer),
+            (Profession::Necromancer, &mut self.necromancer),
+        ]
+        .into_iter()
+    }
+}
+
+impl Default for ProfessionColors {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Colours that are looked up from the view's [`ColorSettings`] when a line is rendered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorRole {
+    SquadChat,
+    SquadUser,
+    PartyChat,
+    PartyUser,
+}
+
+impl ColorSettings {
+    pub fn color(&self, role: ColorRole) -> [f32; 4] {
+        match role {
+            ColorRole::SquadChat => self.squad_chat,
+            ColorRole::SquadUser => self.squad_user,
+            ColorRole::PartyChat => self.party_chat,
+            ColorRole::PartyUser => self.party_user,
+        }
+    }
+}
+
+/// How times are shown in the squad log. Lines keep the time they were logged at, so
+/// changes apply to the whole log.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TimestampSettings {
+    pub clock_24h: bool,
+    pub seconds: bool,
+    pub date: bool,
+    /// Shows how long ago a line was logged instead, like "2m ago".
+    pub relative: bool,
+    /// Shows server time, which is UTC, instead of local time.
+    pub utc: bool,
+    /// Shows the date where the day changes between two lines that are shown, so with a
+    /// filter it comes before the first matching line of each day.
+    pub day_separators: bool,
+}
+
+impl TimestampSettings {
+    pub fn new() -> Self {
+        Self {
+            clock_24h: true,
+            seconds: true,
+            date: false,
+            relative: false,
+            utc: false,
+            day_separators: true,
+        }
+    }
+
+    pub fn format(&self, time: DateTime<Local>, now: DateTime<Local>) -> String {
+        if self.relative {
+            let seconds = (now - time).num_seconds().max(0);
+            return match seconds {
+                0..=59 => format!("{}s ago", seconds),
+                60..=3599 => format!("{}m ago", seconds / 60),
+                3600..=86399 => format!("{}h ago", seconds / 3600),
+                _ => format!("{}d ago", seconds / 86400),
+            };
+        }
+        let mut format = String::new();
+        if self.date {
+            format.push_str("%Y-%m-%d ");
+        }
+        format.push_str(if self.clock_24h { "%H:%M" } else { "%I:%M" });
+        if self.seconds {
+            format.push_str(":%S");
+        }
+        if !self.clock_24h {
+            format.push_str(" %p");
+        }
+        if self.utc {
+            time.with_timezone(&Utc).format(&format).to_string()
+        } else {
+            time.format(&format).to_string()
+        }
+    }
+
+    /// The day `time` falls on, in the chosen time zone.
+    pub fn day(&self, time: DateTime<Local>) -> NaiveDate {
+        if self.utc {
+            time.with_timezone(&Utc).date_naive()
+        } else {
+            time.date_naive()
+        }
+    }
+}
+
+impl Default for TimestampSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A tab in the squad log, showing the shared buffer through its own filters and colours.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogViewSettings {
+    pub name: String,
+    pub text_filter: String,
+    pub filter_settings: FilterSettings,
+    pub color_settings: ColorSettings,
+}
+
+impl LogViewSettings {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            text_filter: String::new(),
+            filter_settings: FilterSettings::new(),
+            color_settings: ColorSettings::new(),
+        }
+    }
+}
+
+impl Default for LogViewSettings {
+    fn default() -> Self {
+        Self::new("All")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HighlightChannel {
+    Any,
+    Squad,
+    Party,
+}
+
+impl HighlightChannel {
+    pub const ALL: [HighlightChannel; 3] = [Self::Any, Self::Squad, Self::Party];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Any => "Any channel",
+            Self::Squad => "Squad",
+            Self::Party => "Party",
+        }
+    }
+}
+
+/// Highlights matching text in chat messages.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HighlightRule {
+    pub enabled: bool,
+    /// A word or phrase, or a regular expression if `regex` is set.
+    pub pattern: String,
+    pub regex: bool,
+    pub case_sensitive: bool,
+    pub channel: HighlightChannel,
+    /// Only highlight messages from this account, if not empty.
+    pub account: String,
+    pub color: [f32; 4],
+    pub bold: bool,
+    /// Fill behind the match with `color` instead of colouring the text.
+    pub background: bool,
+}
+
+impl HighlightRule {
+    pub fn new() -> Self {
+        Self {
+            enabled: true,
+            pattern: String::new(),
+            regex: false,
+            case_sensitive: false,
+            channel: HighlightChannel::Any,
+            account: String::new(),
+            color: [1.0, 0.85, 0.3, 1.0],
+            bold: false,
+            background: false,
+        }
+    }
+}
+
+impl Default for HighlightRule {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct ChatLogSettings {
     pub log_enabled: bool,
     pub log_path: String,
     pub log_buffer: i32,
+    pub backfill_messages: i32,
+    pub backup_settings: BackupSettings,
+    pub views: Vec<LogViewSettings>,
+    pub highlight_rules: Vec<HighlightRule>,
+    /// JSON file with extra names for chat links, on top of the bundled ones.
+    pub chat_link_names_path: String,
+    pub timestamps: TimestampSettings,
+    /// Collapse messages an account repeats into one line with a count.
+    pub collapse_repeats: bool,
+    /// Seconds after an account's last message that the same text still counts as a repeat.
+    pub repeat_window: i32,
+    /// Group bursts of instance and combat updates into one expandable line.
+    pub group_bursts: bool,
+    /// Log the squad members who aren't in our instance when a ready check starts.
+    pub missing_members_on_ready_check: bool,
+    /// Minutes between logging the squad members who aren't in our instance, 0 for never.
+    pub missing_members_interval: i32,
+    /// Only read to migrate settings from before views were added.
+    #[serde(skip_serializing)]
     pub color_settings: ColorSettings,
+    /// Only read to migrate settings from before views were added.
+    #[serde(skip_serializing)]
     pub filter_settings: FilterSettings,
     pub hotkey: Option<u32>,
     pub show_filters: bool,
@@ -83,6 +380,17 @@ impl ChatLogSettings {
             log_enabled: false,
             log_path: Self::default_log_path().to_str().unwrap().to_string(),
             log_buffer: 10000,
+            backfill_messages: 0,
+            backup_settings: BackupSettings::new(),
+            views: Vec::new(),
+            highlight_rules: Vec::new(),
+            chat_link_names_path: String::new(),
+            timestamps: TimestampSettings::new(),
+            collapse_repeats: true,
+            repeat_window: 60,
+            group_bursts: true,
+            missing_members_on_ready_check: true,
+            missing_members_interval: 0,
             color_settings: ColorSettings::new(),
             filter_settings: FilterSettings::new(),
             hotkey: Some(LogUi::DEFAULT_HOTKEY),
@@ -91,6 +399,18 @@ impl ChatLogSettings {
         }
     }
 
+    /// Makes sure there is at least one view, carrying over the filters and colours
+    /// from settings saved before views were added.
+    pub fn migrate_views(&mut self) {
+        if self.views.is_empty() {
+            self.views.push(LogViewSettings {
+                filter_settings: self.filter_settings,
+                color_settings: self.color_settings,
+                ..LogViewSettings::default()
+            });
+        }
+    }
+
     fn default_log_path() -> PathBuf {
         arcdps::exports::config_path()
             .map(|mut path| {
@@ -124,3 +444,40 @@ impl HasSettings for LogUi {
         self.update_settings();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    #[test]
+    fn test_timestamp_format() {
+        let time = Local.with_ymd_and_hms(2024, 3, 10, 15, 4, 5).unwrap();
+        let mut timestamps = TimestampSettings::new();
+        assert_eq!(timestamps.format(time, time), "15:04:05");
+
+        timestamps.clock_24h = false;
+        timestamps.seconds = false;
+        timestamps.date = true;
+        assert_eq!(timestamps.format(time, time), "2024-03-10 03:04 PM");
+
+        timestamps.relative = true;
+        assert_eq!(
+            timestamps.format(time, time + Duration::seconds(5)),
+            "5s ago"
+        );
+        assert_eq!(
+            timestamps.format(time, time + Duration::minutes(2)),
+            "2m ago"
+        );
+        assert_eq!(
+            timestamps.format(time, time + Duration::hours(25)),
+            "1d ago"
+        );
+        // times slightly in the future, from clock differences, aren't negative
+        assert_eq!(
+            timestamps.format(time, time - Duration::seconds(1)),
+            "0s ago"
+        );
+    }
+}
@@ -13,6 +13,7 @@ use log::error;
 
 use crate::{
     db::{
+        bookmarks::{BookmarkTarget, BookmarkToAdd, MessageKey},
         insert::{NoteColorUpdate, NoteToAdd},
         query::QueriedNote,
         ChatDatabase,
@@ -20,49 +21,89 @@ use crate::{
     tracking::Tracker,
 };
 
-use super::LogUi;
+use super::{
+    buffer::LogLine,
+    filter::TextFilter,
+    query::FilterQuery,
+    settings::{ColorSettings, HighlightChannel, HighlightRule, LogViewSettings},
+    visible::FilterKey,
+    LogUi,
+};
 
 const DATETIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+const DAY_FORMAT: &str = "%A, %Y-%m-%d";
 
 impl Windowable<&Tracker> for LogUi {
     const CONTEXT_MENU: bool = true;
     const DEFAULT_OPTIONS: bool = true;
 
     fn render_menu(&mut self, ui: &Ui, _props: &mut &Tracker) {
-        ui.checkbox(
-            "Hover character names for account names",
-            &mut self
-                .settings
-                .filter_settings
-                .hover_char_name_for_account_name,
-        );
         ui.checkbox("Show text filter", &mut self.settings.show_filters);
         ui.checkbox("Show seen users", &mut self.settings.show_seen_users);
+        ui.checkbox("Pause autoscroll", &mut self.ui_props.autoscroll_paused);
         ui.separator();
-        ui.checkbox("Squad", &mut self.settings.filter_settings.squad_message);
+
+        let views = &mut self.settings.views;
+        let view = &mut views[self.ui_props.active_view];
+        ui.set_next_item_width(render::ch_width(ui, 20));
+        ui.input_text("View name", &mut view.name).build();
+        ui.checkbox(
+            "Hover character names for account names",
+            &mut view.filter_settings.hover_char_name_for_account_name,
+        );
+        ui.checkbox("Squad", &mut view.filter_settings.squad_message);
         if ui.is_item_hovered() {
             ui.tooltip_text("/squad messages");
         }
         ui.same_line();
-        ui.checkbox("Party", &mut self.settings.filter_settings.party_message);
+        ui.checkbox("Party", &mut view.filter_settings.party_message);
         if ui.is_item_hovered() {
             ui.tooltip_text("/party messages");
         }
         ui.same_line();
-        ui.checkbox("Updates", &mut self.settings.filter_settings.squad_updates);
+        ui.checkbox("Updates", &mut view.filter_settings.squad_updates);
         if ui.is_item_hovered() {
             ui.tooltip_text("Joins, leaves, subgroup/role changes, instance changes, ready checks");
         }
         ui.same_line();
-        ui.checkbox("Combat", &mut self.settings.filter_settings.combat_updates);
+        ui.checkbox("Combat", &mut view.filter_settings.combat_updates);
         if ui.is_item_hovered() {
             ui.tooltip_text("Entering and exiting combat");
         }
         ui.same_line();
-        ui.checkbox("Others", &mut self.settings.filter_settings.others);
+        ui.checkbox("Others", &mut view.filter_settings.others);
         if ui.is_item_hovered() {
             ui.tooltip_text("Messages that don't fit in any other category");
         }
+        if let Some(_colors) = ui.begin_menu("Colors") {
+            let colors = &mut view.color_settings;
+            ui.color_edit4("Squad chat", &mut colors.squad_chat);
+            ui.color_edit4("Squad names", &mut colors.squad_user);
+            ui.color_edit4("Party chat", &mut colors.party_chat);
+            ui.color_edit4("Party names", &mut colors.party_user);
+            ui.checkbox("Color names by profession", &mut colors.by_profession);
+            if ui.is_item_hovered() {
+                ui.tooltip_text("Characters seen in your instance, in the log and seen users");
+            }
+            if colors.by_profession {
+                for (profession, color) in colors.professions.iter_mut() {
+                    ui.color_edit4(format!("{:?}", profession), color);
+                }
+            }
+            if ui.button("Reset colors") {
+                *colors = ColorSettings::new();
+            }
+        }
+        if ui.button("Add view") {
+            views.push(LogViewSettings::new(&format!("View {}", views.len() + 1)));
+        }
+        if views.len() > 1 {
+            ui.same_line();
+            if ui.button("Remove view") {
+                views.remove(self.ui_props.active_view);
+                self.ui_props.active_view = self.ui_props.active_view.min(views.len() - 1);
+            }
+        }
         ui.separator();
     }
 }
@@ -72,13 +113,85 @@ impl Component<&Tracker> for LogUi {
         let _style = render::small_padding(ui);
         let _border_style = ui.push_style_var(StyleVar::ChildBorderSize(1.0));
 
+        if self.settings.views.len() > 1 {
+            if let Some(_tab_bar) = ui.tab_bar("chat_log_views") {
+                for (i, view) in self.settings.views.iter().enumerate() {
+                    if let Some(_tab) = ui.tab_item(format!("{}###chatlogview{}", view.name, i)) {
+                        self.ui_props.active_view = i;
+                    }
+                }
+            }
+        }
+        let view = &mut self.settings.views[self.ui_props.active_view];
+
+        let frame = ui.frame_count();
+        if self
+            .ui_props
+            .last_frame
+            .is_some_and(|last_frame| frame > last_frame + 1)
+        {
+            let seen_end = self.ui_props.seen_end;
+            self.ui_props.unread_marker = (self.buffer.end() > seen_end).then_some(seen_end);
+        }
+        self.ui_props.last_frame = Some(frame);
+
+        let filter_settings = &view.filter_settings;
+        // parsed every frame so relative times like after:1h stay current
+        self.ui_props.query = FilterQuery::parse(&view.text_filter, chrono::Local::now());
+        self.ui_props.text_matcher.update(
+            &self.ui_props.query.text,
+            filter_settings.case_sensitive,
+            filter_settings.regex,
+        );
+        self.ui_props.account_matcher.update(
+            &self.ui_props.account_filter,
+            filter_settings.case_sensitive,
+            filter_settings.regex,
+        );
+
+        let colors = exports::colors();
+        let red = colors
+            .core(CoreColor::LightRed)
+            .unwrap_or([1.0, 0.0, 0.0, 1.0]);
+
         if self.settings.show_filters {
-            ui.input_text("Filter", &mut self.ui_props.text_filter)
-                .build();
+            ui.input_text("Filter", &mut view.text_filter).build();
+            if ui.is_item_hovered() {
+                ui.tooltip_text(
+                    "Supports from:, account:, char:, channel:, sub:, type:, after: and before:\nfor example: from:Foo.1234 channel:party sub:3 \"stack\"",
+                );
+            }
+            ui.same_line();
+            ui.checkbox("Aa", &mut view.filter_settings.case_sensitive);
+            if ui.is_item_hovered() {
+                ui.tooltip_text("Match case");
+            }
+            ui.same_line();
+            ui.checkbox(".*", &mut view.filter_settings.regex);
+            if ui.is_item_hovered() {
+                ui.tooltip_text("Use regular expressions");
+            }
+            if let Some(err) = self
+                .ui_props
+                .query
+                .error()
+                .or(self.ui_props.text_matcher.error())
+            {
+                ui.text_colored(red, err);
+            }
         }
 
         if let Some(_child) = ui.child_window("chat_log_child_window").begin() {
             if self.settings.show_seen_users {
+                let name_colors = view.color_settings;
+                let professions = &self.buffer.professions;
+                // characters are listed by themselves when coloured by profession
+                let character_color =
+                    name_colors.by_profession.then_some(|character_name: &str| {
+                        professions
+                            .get(character_name)
+                            .and_then(|(_, profession)| name_colors.profession_color(*profession))
+                    });
                 if let Some(_child) = ui
                     .child_window("chat_log_names")
                     .horizontal_scrollbar(true)
@@ -90,6 +203,9 @@ impl Component<&Tracker> for LogUi {
                     ui.set_next_item_width(-ui.calc_text_size("Filter")[0] - 5.0);
                     ui.input_text("Filter", &mut self.ui_props.account_filter)
                         .build();
+                    if self.ui_props.account_matcher.error().is_some() {
+                        ui.text_colored(red, "Invalid pattern");
+                    }
                     if let Some(_child) = ui.child_window("chat_log_names_child").begin() {
                         ui.text_disabled("Tracked");
                         tracker
@@ -98,7 +214,7 @@ impl Component<&Tracker> for LogUi {
                             .filter(|(account_name, _)| tracker.map.contains_key(*account_name))
                             .filter(|(account_name, character_names)| {
                                 LogUi::filter_user(
-                                    &self.ui_props.account_filter,
+                                    &self.ui_props.account_matcher,
                                     account_name,
                                     character_names,
                                 )
@@ -106,10 +222,12 @@ impl Component<&Tracker> for LogUi {
                             .for_each(|(account_name, character_names)| {
                                 LogUi::render_user(
                                     &self.chat_database,
-                                    &mut self.ui_props.text_filter,
+                                    &mut view.text_filter,
+                                    &mut self.ui_props.purge_request,
                                     ui,
                                     account_name,
                                     character_names,
+                                    character_color.as_ref(),
                                 )
                             });
                         ui.separator();
@@ -120,7 +238,7 @@ impl Component<&Tracker> for LogUi {
                             .filter(|(account_name, _)| !tracker.map.contains_key(*account_name))
                             .filter(|(account_name, character_names)| {
                                 LogUi::filter_user(
-                                    &self.ui_props.account_filter,
+                                    &self.ui_props.account_matcher,
                                     account_name,
                                     character_names,
                                 )
@@ -128,10 +246,12 @@ impl Component<&Tracker> for LogUi {
                             .for_each(|(account_name, character_names)| {
                                 LogUi::render_user(
                                     &self.chat_database,
-                                    &mut self.ui_props.text_filter,
+                                    &mut view.text_filter,
+                                    &mut self.ui_props.purge_request,
                                     ui,
                                     account_name,
                                     character_names,
+                                    character_color.as_ref(),
                                 )
                             });
                     }
@@ -144,56 +264,292 @@ impl Component<&Tracker> for LogUi {
                 ui.same_line_with_spacing(0.0, 0.0);
             }
 
+            let bookmarkable = self.settings.log_enabled && self.chat_database.is_some();
             if let Some(_child) = ui
                 .child_window("chat_log")
                 .child_flags(ChildFlags::BORDERS)
                 .begin()
             {
-                self.buffer
-                    .buffer
-                    .iter()
-                    .filter(|x| {
-                        x.filter(&self.ui_props.text_filter, &self.settings.filter_settings)
-                    })
-                    .for_each(|x| {
-                        x.render(
-                            ui,
-                            self.settings
-                                .filter_settings
-                                .hover_char_name_for_account_name,
-                        )
+                let key = FilterKey {
+                    view: self.ui_props.active_view,
+                    text_filter: view.text_filter.clone(),
+                    filter_settings: view.filter_settings,
+                };
+                let changed = self.buffer.take_changed();
+                let query = &self.ui_props.query;
+                let text_matcher = &self.ui_props.text_matcher;
+                let buffer = &self.buffer;
+                let matches = |index| {
+                    buffer
+                        .line(index)
+                        .is_some_and(|line| line.filter(query, text_matcher, &view.filter_settings))
+                };
+                self.ui_props.visible.update(
+                    &key,
+                    buffer.generation,
+                    buffer.dropped,
+                    buffer.end(),
+                    matches,
+                );
+                // relative times like after:1h move with the clock, so only the lines
+                // between the old and new bounds can have changed
+                let time_bounds = [query.after, query.before];
+                let moved = self
+                    .ui_props
+                    .time_bounds
+                    .into_iter()
+                    .zip(time_bounds)
+                    .filter_map(|bounds| match bounds {
+                        (Some(old), Some(new)) if old != new => {
+                            Some(buffer.time_range(old.min(new), old.max(new)))
+                        }
+                        _ => None,
                     });
-                if ui.scroll_y() >= ui.scroll_max_y() {
+                for index in moved.flatten().chain(changed) {
+                    self.ui_props.visible.refresh(index, matches(index));
+                }
+                self.ui_props.time_bounds = time_bounds;
+                let marker = self
+                    .ui_props
+                    .unread_marker
+                    .and_then(|marker| self.ui_props.visible.first_from(marker));
+                let timestamps = &self.settings.timestamps;
+                self.ui_props.visible.render(ui, |index, previous| {
+                    let Some(line) = buffer.line(index) else {
+                        return;
+                    };
+                    if timestamps.day_separators {
+                        let day = timestamps.day(line.time);
+                        if previous
+                            .and_then(|previous| buffer.line(previous))
+                            .is_some_and(|previous| timestamps.day(previous.time) != day)
+                        {
+                            ui.text_disabled(day.format(DAY_FORMAT).to_string());
+                            ui.separator();
+                        }
+                    }
+                    if marker == Some(index) {
+                        let _color = ui.push_style_color(StyleColor::Separator, red);
+                        ui.text_colored(red, "New messages");
+                        ui.separator();
+                    }
+                    if line.render(
+                        ui,
+                        view.filter_settings.hover_char_name_for_account_name,
+                        bookmarkable,
+                        &view.color_settings,
+                        timestamps,
+                    ) {
+                        LogUi::bookmark_line(&self.chat_database, line);
+                    }
+                });
+
+                let at_bottom = ui.scroll_y() >= ui.scroll_max_y();
+                let end = buffer.end();
+                let props = &mut self.ui_props;
+                if (at_bottom && !props.autoscroll_paused) || props.jump_to_bottom {
                     ui.set_scroll_here_y_with_ratio(1.0);
+                    props.jump_to_bottom = false;
+                    props.unread_from = None;
+                } else if end > props.seen_end {
+                    props.unread_from.get_or_insert(props.seen_end);
+                } else if at_bottom {
+                    props.unread_from = None;
+                }
+                props.seen_end = end;
+
+                let unread = props
+                    .unread_from
+                    .map_or(0, |unread_from| props.visible.count_from(unread_from));
+                if unread > 0 {
+                    let label = match unread {
+                        1 => "1 new message".to_owned(),
+                        unread => format!("{} new messages", unread),
+                    };
+                    let size = ui.calc_text_size(&label);
+                    let style = ui.clone_style();
+                    let [width, height] = ui.window_size();
+                    ui.set_cursor_pos([
+                        ui.scroll_x() + width
+                            - size[0]
+                            - style.frame_padding[0] * 2.0
+                            - style.scrollbar_size
+                            - style.window_padding[0],
+                        ui.scroll_y() + height
+                            - size[1]
+                            - style.frame_padding[1] * 2.0
+                            - style.window_padding[1],
+                    ]);
+                    if ui.button(label) {
+                        props.jump_to_bottom = true;
+                    }
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text("Jump to the newest message");
+                    }
                 }
             }
         }
+
+        self.render_purge_confirm(ui);
     }
 }
 
 impl LogUi {
+    pub fn render_highlight_settings(&mut self, ui: &Ui) {
+        let red = exports::colors()
+            .core(CoreColor::LightRed)
+            .unwrap_or([1.0, 0.0, 0.0, 1.0]);
+        ui.text_disabled(
+            "Matching text in chat messages is highlighted. Earlier rules take priority.",
+        );
+        let mut changed = false;
+        let mut remove = None;
+        for (i, rule) in self.settings.highlight_rules.iter_mut().enumerate() {
+            ui.separator();
+            changed |= ui.checkbox(format!("##highlightenabled{}", i), &mut rule.enabled);
+            ui.same_line();
+            ui.set_next_item_width(render::ch_width(ui, 24));
+            ui.input_text(format!("##highlightpattern{}", i), &mut rule.pattern)
+                .hint("Word or pattern")
+                .build();
+            changed |= ui.is_item_deactivated_after_edit();
+            ui.same_line();
+            changed |= ui.checkbox(format!("Aa##highlight{}", i), &mut rule.case_sensitive);
+            if ui.is_item_hovered() {
+                ui.tooltip_text("Match case");
+            }
+            ui.same_line();
+            changed |= ui.checkbox(format!(".*##highlight{}", i), &mut rule.regex);
+            if ui.is_item_hovered() {
+                ui.tooltip_text("Use a regular expression instead of matching whole words");
+            }
+            ui.same_line();
+            ui.color_edit4_config(format!("##highlightcolor{}", i), &mut rule.color)
+                .inputs(false)
+                .build();
+            changed |= ui.is_item_deactivated_after_edit();
+
+            ui.set_next_item_width(render::ch_width(ui, 14));
+            if let Some(_combo) =
+                ui.begin_combo(format!("##highlightchannel{}", i), rule.channel.label())
+            {
+                for channel in HighlightChannel::ALL {
+                    if ui
+                        .selectable_config(channel.label())
+                        .selected(channel == rule.channel)
+                        .build()
+                    {
+                        rule.channel = channel;
+                        changed = true;
+                    }
+                }
+            }
+            ui.same_line();
+            ui.set_next_item_width(render::ch_width(ui, 20));
+            ui.input_text(format!("##highlightaccount{}", i), &mut rule.account)
+                .hint("Any account")
+                .build();
+            changed |= ui.is_item_deactivated_after_edit();
+            ui.same_line();
+            changed |= ui.checkbox(format!("Bold##highlight{}", i), &mut rule.bold);
+            ui.same_line();
+            changed |= ui.checkbox(format!("Background##highlight{}", i), &mut rule.background);
+            if ui.is_item_hovered() {
+                ui.tooltip_text(
+                    "Fill behind the text with the colour instead of colouring the text",
+                );
+            }
+            ui.same_line();
+            if ui.button(format!("Remove##highlight{}", i)) {
+                remove = Some(i);
+            }
+            if let Some(err) = self.buffer.highlighter.error(i) {
+                ui.text_colored(red, err);
+            }
+        }
+        if let Some(i) = remove {
+            self.settings.highlight_rules.remove(i);
+            changed = true;
+        }
+        ui.separator();
+        if ui.button("Add highlight rule") {
+            self.settings.highlight_rules.push(HighlightRule::new());
+        }
+        if changed {
+            self.update_highlights();
+        }
+    }
+
+    fn render_purge_confirm(&mut self, ui: &Ui) {
+        const POPUP_ID: &str = "Forget account?##purgeconfirm";
+        // the seen users context menu can't open the modal itself as it has a different id stack
+        if let Some(account_name) = self.ui_props.purge_request.take() {
+            self.ui_props.purge_confirm = Some(account_name);
+            ui.open_popup(POPUP_ID);
+        }
+        if let Some(_popup) = ui
+            .modal_popup_config(POPUP_ID)
+            .always_auto_resize(true)
+            .begin_popup()
+        {
+            let account_name = self.ui_props.purge_confirm.clone().unwrap_or_default();
+            ui.text(format!(
+                "Delete all messages, notes and bookmarks for {}\nand remove it from the seen users and squad log?",
+                account_name
+            ));
+            ui.text_disabled("This cannot be undone.");
+            if ui.button("Forget") {
+                self.purge_account = Some(account_name);
+                self.ui_props.purge_confirm = None;
+                ui.close_current_popup();
+            }
+            ui.same_line();
+            if ui.button("Cancel") {
+                self.ui_props.purge_confirm = None;
+                ui.close_current_popup();
+            }
+        }
+    }
+
+    fn bookmark_line(chat_database: &Option<Arc<Mutex<ChatDatabase>>>, line: &LogLine) {
+        if let (Some(chat_database), Some(message)) = (chat_database, &line.message) {
+            if let Err(err) = chat_database
+                .lock()
+                .unwrap()
+                .add_bookmark(BookmarkToAdd::new(
+                    BookmarkTarget::Message(MessageKey::new(message)),
+                    "",
+                ))
+            {
+                error!("failed to add bookmark: {:#}", err);
+            }
+        }
+    }
+
     fn filter_user(
-        account_filter: &String,
+        account_filter: &TextFilter,
         account_name: &str,
         character_names: &HashSet<String>,
     ) -> bool {
         account_filter.is_empty()
-            || account_name.contains(account_filter)
+            || account_filter.matches(account_name)
             || character_names
                 .iter()
-                .any(|character_name| character_name.contains(account_filter))
+                .any(|character_name| account_filter.matches(character_name))
     }
 
     fn render_user(
         chat_database: &Option<Arc<Mutex<ChatDatabase>>>,
         text_filter: &mut String,
+        purge_request: &mut Option<String>,
         ui: &Ui,
         account_name: &str,
         character_names: &HashSet<String>,
+        character_color: Option<&impl Fn(&str) -> Option<[f32; 4]>>,
     ) {
         ui.separator();
         let mut label = account_name.to_owned();
-        if !character_names.is_empty() {
+        if !character_names.is_empty() && character_color.is_none() {
             label = format!(
                 "{}\n{}",
                 label,
@@ -286,6 +642,11 @@ impl LogUi {
             } else {
                 ui.text_disabled("Database not available")
             }
+            ui.separator();
+            if ui.button("Forget account") {
+                *purge_request = Some(account_name.to_owned());
+                ui.close_current_popup();
+            }
         });
         if ui.is_item_hovered() {
             let _tooltip = ui.begin_tooltip();
@@ -319,6 +680,15 @@ impl LogUi {
                 ui.text_disabled("Database not available")
             }
         }
+        if let Some(character_color) = character_color {
+            for character_name in character_names {
+                let text = format!("- {}", character_name);
+                match character_color(character_name) {
+                    Some(color) => ui.text_colored(color, text),
+                    None => ui.text(text),
+                }
+            }
+        }
     }
 }
 
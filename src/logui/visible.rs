@@ -0,0 +1,237 @@
+use std::collections::VecDeque;
+
+use arc_util::ui::Ui;
+
+use super::settings::FilterSettings;
+
+/// Everything the filtered lines depend on, other than the buffer itself.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilterKey {
+    pub view: usize,
+    pub text_filter: String,
+    pub filter_settings: FilterSettings,
+}
+
+#[derive(Debug)]
+struct VisibleLine {
+    /// Index of the line in the buffer, see `LogBuffer::line`.
+    index: u64,
+    /// Height of the line when it was last rendered, or one row if it hasn't been.
+    height: f32,
+    /// Whether `height` was measured rather than assumed.
+    measured: bool,
+    /// Sum of the heights of the lines before, counted from an arbitrary origin so lines
+    /// can be dropped from the front without updating the rest.
+    offset: f32,
+}
+
+/// The lines of the buffer that pass the filters of the current view, so only new lines
+/// need filtering each frame and only the lines on screen need rendering.
+#[derive(Debug, Default)]
+pub struct VisibleLines {
+    key: Option<FilterKey>,
+    generation: u64,
+    /// Index of the first line that hasn't been filtered yet.
+    end: u64,
+    lines: VecDeque<VisibleLine>,
+    /// Position of the first line whose offset is out of date.
+    stale_from: usize,
+    /// Height assumed for lines that haven't been rendered yet.
+    row_height: f32,
+}
+
+impl VisibleLines {
+    /// Brings the lines up to date with the buffer, which holds the lines from `first` up
+    /// to `end`. Everything is filtered again when the key or buffer generation changes,
+    /// otherwise only lines added since the last update are.
+    pub fn update(
+        &mut self,
+        key: &FilterKey,
+        generation: u64,
+        first: u64,
+        end: u64,
+        mut matches: impl FnMut(u64) -> bool,
+    ) {
+        if self.key.as_ref() != Some(key) || self.generation != generation || end < self.end {
+            self.key = Some(key.clone());
+            self.generation = generation;
+            self.end = first;
+            self.lines.clear();
+            self.stale_from = 0;
+        }
+        while self.lines.front().is_some_and(|line| line.index < first) {
+            self.lines.pop_front();
+            self.stale_from = self.stale_from.saturating_sub(1);
+        }
+        for index in self.end.max(first)..end {
+            if matches(index) {
+                self.stale_from = self.stale_from.min(self.lines.len());
+                self.lines.push_back(self.new_line(index));
+            }
+        }
+        self.end = end;
+    }
+
+    fn new_line(&self, index: u64) -> VisibleLine {
+        VisibleLine {
+            index,
+            height: self.row_height,
+            measured: false,
+            offset: 0.0,
+        }
+    }
+
+    /// Updates whether the line at `index`, which changed in place or moved across a
+    /// time bound of the query, is visible. Lines that haven't been filtered yet are left
+    /// for `update`.
+    pub fn refresh(&mut self, index: u64, matches: bool) {
+        if index >= self.end {
+            return;
+        }
+        let position = self.lines.partition_point(|line| line.index < index);
+        let visible = self
+            .lines
+            .get(position)
+            .is_some_and(|line| line.index == index);
+        match (visible, matches) {
+            (true, true) => {}
+            (true, false) => {
+                self.lines.remove(position);
+                self.stale_from = self.stale_from.min(position);
+            }
+            (false, true) => {
+                self.lines.insert(position, self.new_line(index));
+                self.stale_from = self.stale_from.min(position);
+            }
+            (false, false) => {}
+        }
+    }
+
+    /// Number of lines at or after the buffer index `index`.
+    pub fn count_from(&self, index: u64) -> usize {
+        self.lines.len() - self.lines.partition_point(|line| line.index < index)
+    }
+
+    /// Buffer index of the first line at or after `index`.
+    pub fn first_from(&self, index: u64) -> Option<u64> {
+        self.lines
+            .get(self.lines.partition_point(|line| line.index < index))
+            .map(|line| line.index)
+    }
+
+    /// Brings the offsets of the lines up to date, returning the height of all lines.
+    fn update_offsets(&mut self) -> f32 {
+        for position in self.stale_from.max(1)..self.lines.len() {
+            let previous = &self.lines[position - 1];
+            self.lines[position].offset = previous.offset + previous.height;
+        }
+        self.stale_from = self.lines.len();
+        match (self.lines.front(), self.lines.back()) {
+            (Some(first), Some(last)) => last.offset + last.height - first.offset,
+            _ => 0.0,
+        }
+    }
+
+    /// Renders the lines that are scrolled into view with `render_line`, which is also
+    /// given the line before, leaving space for the rest. The first line in view is found
+    /// by a binary search over the line offsets. Lines are measured as they are rendered,
+    /// and when the first one, partly above the view, turns out a different height the
+    /// scroll position moves with it so the lines below don't jump.
+    pub fn render(&mut self, ui: &Ui, mut render_line: impl FnMut(u64, Option<u64>)) {
+        let row_height = ui.text_line_height_with_spacing();
+        if row_height != self.row_height {
+            self.row_height = row_height;
+            for line in self.lines.iter_mut().filter(|line| !line.measured) {
+                line.height = row_height;
+            }
+            self.stale_from = 0;
+        }
+        self.update_offsets();
+        let origin = self.lines.front().map_or(0.0, |line| line.offset);
+        let top = ui.scroll_y();
+        let bottom = top + ui.window_size()[1];
+        let [x, start] = ui.cursor_pos();
+        let first = self
+            .lines
+            .partition_point(|line| start + line.offset - origin + line.height < top);
+        let mut scroll_adjustment = 0.0;
+        let mut y = start
+            + self
+                .lines
+                .get(first)
+                .map_or(0.0, |line| line.offset - origin);
+        for position in first..self.lines.len() {
+            if y > bottom {
+                break;
+            }
+            let previous = position
+                .checked_sub(1)
+                .map(|previous| self.lines[previous].index);
+            ui.set_cursor_pos([x, y]);
+            render_line(self.lines[position].index, previous);
+            let height = ui.cursor_pos()[1] - y;
+            let line = &mut self.lines[position];
+            if line.height != height {
+                if y < top {
+                    scroll_adjustment += height - line.height;
+                }
+                line.height = height;
+                self.stale_from = self.stale_from.min(position + 1);
+            }
+            line.measured = true;
+            y += height;
+        }
+        let total = self.update_offsets();
+        ui.set_cursor_pos([x, start + total]);
+        ui.dummy([0.0, 0.0]);
+        if scroll_adjustment != 0.0 {
+            ui.set_scroll_y(top + scroll_adjustment);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn indices(visible: &VisibleLines) -> Vec<u64> {
+        visible.lines.iter().map(|line| line.index).collect()
+    }
+
+    #[test]
+    fn test_update() {
+        let key = FilterKey {
+            view: 0,
+            text_filter: String::new(),
+            filter_settings: FilterSettings::new(),
+        };
+        let mut visible = VisibleLines::default();
+        let mut filtered = Vec::new();
+        let mut even = |index: u64| {
+            filtered.push(index);
+            [0, 2, 4, 6].contains(&index)
+        };
+        visible.update(&key, 0, 0, 5, &mut even);
+        assert_eq!(indices(&visible), [0, 2, 4]);
+
+        // new lines are filtered on their own, and dropped lines are removed
+        visible.update(&key, 0, 3, 8, &mut even);
+        assert_eq!(indices(&visible), [4, 6]);
+        assert_eq!(filtered, [0, 1, 2, 3, 4, 5, 6, 7]);
+
+        // a new generation filters everything again
+        visible.update(&key, 1, 3, 8, |index| [3, 5, 7].contains(&index));
+        assert_eq!(indices(&visible), [3, 5, 7]);
+        assert_eq!(visible.count_from(4), 2);
+        assert_eq!(visible.first_from(4), Some(5));
+        assert_eq!(visible.first_from(8), None);
+        visible.update(&FilterKey { view: 1, ..key }, 1, 3, 8, |index| index == 3);
+        assert_eq!(indices(&visible), [3]);
+
+        // lines changed in place are added or removed on their own
+        visible.refresh(5, true);
+        visible.refresh(3, false);
+        visible.refresh(9, true);
+        assert_eq!(indices(&visible), [5]);
+    }
+}
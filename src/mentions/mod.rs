@@ -0,0 +1,198 @@
+use std::collections::VecDeque;
+
+use arcdps::extras::message::SquadMessageOwned;
+use chrono::Local;
+use regex::Regex;
+
+use crate::{logui::highlight::word_pattern, tracking};
+
+use self::settings::MentionsSettings;
+
+mod settings;
+mod ui;
+
+#[derive(Debug)]
+pub struct Mention {
+    pub id: u64,
+    pub message: SquadMessageOwned,
+    pub time: chrono::DateTime<Local>,
+}
+
+/// Detects chat messages that mention the user, and keeps a list of them.
+#[derive(Debug)]
+pub struct Mentions {
+    pub settings: MentionsSettings,
+    pub list: VecDeque<Mention>,
+    /// Mentions received since the list was last looked at.
+    pub unread: usize,
+    names: Vec<String>,
+    pattern: Option<Regex>,
+    next_id: u64,
+}
+
+impl Mentions {
+    pub fn new() -> Self {
+        Self {
+            settings: MentionsSettings::new(),
+            list: VecDeque::new(),
+            unread: 0,
+            names: Vec::new(),
+            pattern: None,
+            next_id: 0,
+        }
+    }
+
+    /// Rebuilds the pattern from our account and character names, returning whether it changed.
+    pub fn update_names<'a>(
+        &mut self,
+        account_name: &str,
+        character_names: impl Iterator<Item = &'a str>,
+    ) -> bool {
+        let names = mention_names(&self.settings, account_name, character_names);
+        if names == self.names {
+            return false;
+        }
+        self.pattern = (!names.is_empty())
+            .then(|| {
+                let alternation = itertools::join(names.iter().map(|name| word_pattern(name)), "|");
+                Regex::new(&format!("(?i){}", alternation)).ok()
+            })
+            .flatten();
+        self.names = names;
+        true
+    }
+
+    /// The lowercased names currently counted as mentions.
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// The regex currently used to detect mentions.
+    pub fn pattern(&self) -> Option<&str> {
+        self.pattern.as_ref().map(Regex::as_str)
+    }
+
+    pub fn is_mention(&self, message: &SquadMessageOwned, self_account_name: &str) -> bool {
+        message.account_name != self_account_name
+            && self
+                .pattern
+                .as_ref()
+                .is_some_and(|pattern| pattern.is_match(&message.text))
+    }
+
+    /// Adds the message to the list if it mentions the user, returning whether it did.
+    pub fn process_message(
+        &mut self,
+        message: &SquadMessageOwned,
+        self_account_name: &str,
+    ) -> bool {
+        if !self.is_mention(message, self_account_name) {
+            return false;
+        }
+        self.list.push_back(Mention {
+            id: self.next_id,
+            message: message.clone(),
+            time: message
+                .timestamp
+                .map_or_else(Local::now, |timestamp| timestamp.with_timezone(&Local)),
+        });
+        self.next_id += 1;
+        while self.list.len() > self.settings.max_mentions.max(1) as usize {
+            self.list.pop_front();
+        }
+        self.unread += 1;
+        true
+    }
+
+    pub fn clear(&mut self) {
+        self.list.clear();
+        self.unread = 0;
+    }
+
+    /// Removes the mentions sent by `account_name`.
+    pub fn purge_account(&mut self, account_name: &str) {
+        self.list
+            .retain(|mention| mention.message.account_name != account_name);
+        self.unread = self.unread.min(self.list.len());
+    }
+}
+
+impl Default for Mentions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Lists the lowercased names that count as a mention, longest first so the pattern
+/// prefers full names over parts of them.
+fn mention_names<'a>(
+    settings: &MentionsSettings,
+    account_name: &str,
+    character_names: impl Iterator<Item = &'a str>,
+) -> Vec<String> {
+    if !settings.enabled {
+        return Vec::new();
+    }
+    let mut names: Vec<String> = Vec::new();
+    if settings.match_account && !account_name.is_empty() {
+        names.push(account_name.to_owned());
+        if let Some(display_name) = tracking::display_name(account_name) {
+            names.push(display_name.to_owned());
+        }
+    }
+    if settings.match_characters {
+        names.extend(character_names.map(str::to_owned));
+    }
+    names.extend(settings.nicknames.split(',').map(str::to_owned));
+    let mut names: Vec<String> = names
+        .iter()
+        .map(|name| name.trim().to_lowercase())
+        .filter(|name| !name.is_empty())
+        .collect();
+    names.sort_by(|a, b| b.len().cmp(&a.len()).then(a.cmp(b)));
+    names.dedup();
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arcdps::extras::message::{ChannelType, SquadMessageFlags};
+
+    fn message(account_name: &str, text: &str) -> SquadMessageOwned {
+        SquadMessageOwned {
+            channel_id: 0,
+            channel_type: ChannelType::Squad,
+            subgroup: 255,
+            flags: SquadMessageFlags::empty(),
+            timestamp: None,
+            account_name: account_name.to_owned(),
+            character_name: "Someone".to_owned(),
+            text: text.to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_mentions() {
+        let mut mentions = Mentions::new();
+        mentions.settings.nicknames = "commander, cmdr ,".to_owned();
+        assert!(mentions.update_names("Foo.1234", ["Foo Bar"].into_iter()));
+        assert_eq!(
+            mentions.names,
+            ["commander", "foo.1234", "foo bar", "cmdr", "foo"]
+        );
+        assert!(!mentions.update_names("Foo.1234", ["Foo Bar"].into_iter()));
+
+        assert!(mentions.process_message(&message("Baz.5678", "FOO stack on me"), "Foo.1234"));
+        assert!(mentions.process_message(&message("Baz.5678", "thanks cmdr!"), "Foo.1234"));
+        assert!(!mentions.process_message(&message("Baz.5678", "food break"), "Foo.1234"));
+        // our own messages never count
+        assert!(!mentions.process_message(&message("Foo.1234", "foo here"), "Foo.1234"));
+        assert_eq!(mentions.list.len(), 2);
+        assert_eq!(mentions.unread, 2);
+
+        mentions.settings.enabled = false;
+        assert!(mentions.update_names("Foo.1234", ["Foo Bar"].into_iter()));
+        assert!(mentions.pattern().is_none());
+    }
+}
@@ -0,0 +1,51 @@
+use arc_util::settings::HasSettings;
+use serde::{Deserialize, Serialize};
+
+use super::Mentions;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MentionsSettings {
+    pub enabled: bool,
+    /// Match the account name, and the display name before the numbers.
+    pub match_account: bool,
+    /// Match the names of characters seen on our account this session.
+    pub match_characters: bool,
+    /// Extra names to match, separated by commas.
+    pub nicknames: String,
+    pub highlight_color: [f32; 4],
+    pub max_mentions: i32,
+}
+
+impl MentionsSettings {
+    pub fn new() -> Self {
+        Self {
+            enabled: true,
+            match_account: true,
+            match_characters: true,
+            nicknames: String::new(),
+            highlight_color: [0.55, 0.2, 0.2, 1.0],
+            max_mentions: 100,
+        }
+    }
+}
+
+impl Default for MentionsSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HasSettings for Mentions {
+    type Settings = MentionsSettings;
+
+    const SETTINGS_ID: &'static str = "mentions";
+
+    fn current_settings(&self) -> Self::Settings {
+        self.settings.clone()
+    }
+
+    fn load_settings(&mut self, loaded: Self::Settings) {
+        self.settings = loaded;
+    }
+}
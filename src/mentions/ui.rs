@@ -0,0 +1,57 @@
+use arc_util::ui::{
+    render::{self, item_context_menu},
+    Component, Ui, Windowable,
+};
+
+use super::Mentions;
+
+const DATETIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+impl Windowable<()> for Mentions {
+    const CONTEXT_MENU: bool = true;
+    const DEFAULT_OPTIONS: bool = true;
+
+    fn render_menu(&mut self, ui: &Ui, _props: &mut ()) {
+        ui.set_next_item_width(render::ch_width(ui, 10));
+        ui.input_int("Mentions to keep", &mut self.settings.max_mentions)
+            .build();
+        ui.separator();
+    }
+}
+
+impl Component<()> for Mentions {
+    fn render(&mut self, ui: &Ui, _props: ()) {
+        let _style = render::small_padding(ui);
+        // the window is open, so everything in it has been seen
+        self.unread = 0;
+
+        if self.list.is_empty() {
+            ui.text_disabled("Nobody has mentioned you yet");
+            return;
+        }
+        if ui.button("Clear") {
+            self.clear();
+            return;
+        }
+        for mention in self.list.iter().rev() {
+            let message = &mention.message;
+            ui.separator();
+            ui.text_disabled(mention.time.format(DATETIME_FORMAT).to_string());
+            let line = format!(
+                "[{}] {} ({}): {}",
+                message.channel_type, message.character_name, message.account_name, message.text
+            );
+            ui.text_wrapped(&line);
+            item_context_menu(format!("##mentioncontext{}", mention.id), || {
+                if ui.button("Copy line") {
+                    ui.set_clipboard_text(&line);
+                    ui.close_current_popup();
+                }
+                if ui.button("Copy text") {
+                    ui.set_clipboard_text(&message.text);
+                    ui.close_current_popup();
+                }
+            });
+        }
+    }
+}
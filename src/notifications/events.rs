@@ -1,6 +1,7 @@
 use arcdps::extras::message::SquadMessage;
+use chrono::Local;
 
-use crate::MUMBLE_LINK;
+use crate::{audio::AudioTrack, MUMBLE_LINK};
 
 use super::Notifications;
 
@@ -9,7 +10,24 @@ impl Notifications {
         &mut self,
         message: &SquadMessage,
         self_account_name: &str,
+        mentioned: bool,
     ) -> Result<(), anyhow::Error> {
+        let repeat = self.repeats.is_repeat(
+            message.account_name(),
+            message.channel_type,
+            message.subgroup,
+            message.text(),
+            Local::now(),
+        );
+        if repeat && self.settings.skip_repeats {
+            return Ok(());
+        }
+        // mentions have their own sound, which plays regardless of the other ping settings
+        if mentioned && self.settings.ping_on_mention {
+            self.mention_track.set_volume(self.settings.mention_volume);
+            Self::play(&self.mention_track);
+            return Ok(());
+        }
         if !self.settings.ping_on_self_message && message.account_name() == self_account_name {
             return Ok(());
         }
@@ -38,9 +56,13 @@ impl Notifications {
             return;
         }
         self.ping_track.set_volume(self.settings.ping_volume);
-        crate::AUDIO_PLAYER
-            .lock()
-            .unwrap()
-            .play_track(&self.ping_track);
+        Self::play(&self.ping_track);
+    }
+
+    fn play(track: &AudioTrack) {
+        if !track.is_valid() {
+            return;
+        }
+        crate::AUDIO_PLAYER.lock().unwrap().play_track(track);
     }
 }
@@ -2,7 +2,10 @@ use anyhow::Context;
 use arc_util::ui::Component;
 use arcdps::imgui::Ui;
 
-use crate::audio::{sounds, AudioTrack};
+use crate::{
+    audio::{sounds, AudioTrack},
+    repeats::RepeatDetector,
+};
 
 use self::settings::NotificationsSettings;
 
@@ -13,6 +16,8 @@ mod settings;
 pub struct Notifications {
     pub settings: NotificationsSettings,
     pub ping_track: AudioTrack,
+    pub mention_track: AudioTrack,
+    pub repeats: RepeatDetector,
 }
 
 impl Notifications {
@@ -20,6 +25,8 @@ impl Notifications {
         Self {
             settings: NotificationsSettings::new(),
             ping_track: AudioTrack::new(),
+            mention_track: AudioTrack::new(),
+            repeats: RepeatDetector::default(),
         }
     }
 
@@ -29,6 +36,7 @@ impl Notifications {
             .unwrap()
             .set_device(self.settings.audio_device.clone());
         self.update_ping_track()?;
+        self.update_mention_track()?;
         Ok(())
     }
 
@@ -42,6 +50,17 @@ impl Notifications {
             .context("failed to load ping track")?;
         Ok(())
     }
+
+    pub fn update_mention_track(&mut self) -> anyhow::Result<()> {
+        self.mention_track
+            .load_from_path(
+                &self.settings.mention_sound_path,
+                sounds::DEFAULT_PING,
+                self.settings.mention_volume,
+            )
+            .context("failed to load mention track")?;
+        Ok(())
+    }
 }
 
 impl Component<()> for Notifications {
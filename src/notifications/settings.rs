@@ -13,6 +13,11 @@ pub struct NotificationsSettings {
     pub ping_on_self_message: bool,
     pub ping_in_combat: bool,
     pub ping_out_of_combat: bool,
+    pub ping_on_mention: bool,
+    pub mention_volume: i32,
+    pub mention_sound_path: String,
+    /// Don't ping for messages an account repeats, see [`crate::repeats`].
+    pub skip_repeats: bool,
 }
 
 impl NotificationsSettings {
@@ -25,6 +30,10 @@ impl NotificationsSettings {
             ping_on_self_message: false,
             ping_in_combat: true,
             ping_out_of_combat: true,
+            ping_on_mention: true,
+            mention_volume: 100,
+            mention_sound_path: String::new(),
+            skip_repeats: false,
         }
     }
 }
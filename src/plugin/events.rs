@@ -1,13 +1,19 @@
+use std::time::{Duration, Instant};
+
 use arc_util::tracking::Player;
 use arcdps::{
     extras::{ExtrasAddonInfo, Message, SquadMessageOwned, UserInfoIter, UserInfoOwned, UserRole},
     Agent, Event, StateChange,
 };
+use chrono::Local;
 use log::error;
 
-use crate::logui::buffer::LogPart;
+use crate::{
+    logui::buffer::{Burst, LogPart},
+    tracking::ready_check::{self, ReadyCheckState},
+};
 
-use super::{state::ExtrasState, Plugin};
+use super::{state::ExtrasState, Plugin, LOG_WINDOW_TITLE};
 
 impl Plugin {
     pub fn process_message(&mut self, message: &Message) -> Result<(), anyhow::Error> {
@@ -17,14 +23,18 @@ impl Plugin {
         };
         let squad_message_owned: SquadMessageOwned = (*squad_message).into();
         self.tracker.add_player_from_message(squad_message);
-        if let Err(err) = self
-            .notifications
-            .process_message(squad_message, &self.self_account_name)
+        self.update_mention_names();
+        let mentioned = self
+            .mentions
+            .process_message(&squad_message_owned, &self.self_account_name);
+        if let Err(err) =
+            self.notifications
+                .process_message(squad_message, &self.self_account_name, mentioned)
         {
             error!("failed to process message for notifications: {:#}", err);
         }
         self.tts
-            .process_message(&squad_message_owned, &self.self_account_name);
+            .process_message(&squad_message_owned, &self.self_account_name, mentioned);
         self.log_ui.buffer.process_message(&squad_message_owned);
         if self.log_ui.settings.log_enabled {
             if let Some(chat_database) = self.chat_database.as_mut() {
@@ -66,22 +76,31 @@ impl Plugin {
                             let mut parts: Vec<LogPart> = Vec::new();
                             if player.account == self.self_account_name {
                                 parts.push(LogPart::new_no_color("You have entered combat as "));
-                                parts.push(LogPart::new(
-                                    &player.character,
-                                    Some(&player.account),
-                                    None,
-                                    None,
-                                ));
+                                parts.push(
+                                    LogPart::new(
+                                        &player.character,
+                                        Some(&player.account),
+                                        None,
+                                        None,
+                                    )
+                                    .with_profession(Some(player.profession)),
+                                );
+                                self.log_ui.buffer.insert_combat_update_parts(&mut parts);
                             } else {
-                                parts.push(LogPart::new(
-                                    &player.character,
-                                    Some(&player.account),
-                                    None,
-                                    None,
-                                ));
+                                parts.push(
+                                    LogPart::new(
+                                        &player.character,
+                                        Some(&player.account),
+                                        None,
+                                        None,
+                                    )
+                                    .with_profession(Some(player.profession)),
+                                );
                                 parts.push(LogPart::new_no_color(" has entered combat"));
+                                self.log_ui
+                                    .buffer
+                                    .insert_burst_parts(Burst::EnteredCombat, &mut parts);
                             }
-                            self.log_ui.buffer.insert_combat_update_parts(&mut parts);
                         }
                     }
                     StateChange::ExitCombat => {
@@ -89,22 +108,31 @@ impl Plugin {
                             let mut parts: Vec<LogPart> = Vec::new();
                             if player.account == self.self_account_name {
                                 parts.push(LogPart::new_no_color("You have left combat as "));
-                                parts.push(LogPart::new(
-                                    &player.character,
-                                    Some(&player.account),
-                                    None,
-                                    None,
-                                ));
+                                parts.push(
+                                    LogPart::new(
+                                        &player.character,
+                                        Some(&player.account),
+                                        None,
+                                        None,
+                                    )
+                                    .with_profession(Some(player.profession)),
+                                );
+                                self.log_ui.buffer.insert_combat_update_parts(&mut parts);
                             } else {
-                                parts.push(LogPart::new(
-                                    &player.character,
-                                    Some(&player.account),
-                                    None,
-                                    None,
-                                ));
+                                parts.push(
+                                    LogPart::new(
+                                        &player.character,
+                                        Some(&player.account),
+                                        None,
+                                        None,
+                                    )
+                                    .with_profession(Some(player.profession)),
+                                );
                                 parts.push(LogPart::new_no_color(" has left combat"));
+                                self.log_ui
+                                    .buffer
+                                    .insert_burst_parts(Burst::LeftCombat, &mut parts);
                             }
-                            self.log_ui.buffer.insert_combat_update_parts(&mut parts);
                         }
                     }
                     _ => {}
@@ -118,27 +146,41 @@ impl Plugin {
                                 dst.and_then(|dst| Player::from_tracking_change(src, dst))
                             {
                                 self.tracker.add_arc_player(&player);
+                                self.log_ui.buffer.set_profession(
+                                    &player.account,
+                                    &player.character,
+                                    player.profession,
+                                );
                                 let mut parts: Vec<LogPart> = Vec::new();
                                 if player.account == self.self_account_name {
                                     parts.push(LogPart::new_no_color(
                                         "You have joined an instance as ",
                                     ));
-                                    parts.push(LogPart::new(
-                                        &player.character,
-                                        Some(&player.account),
-                                        None,
-                                        None,
-                                    ));
+                                    parts.push(
+                                        LogPart::new(
+                                            &player.character,
+                                            Some(&player.account),
+                                            None,
+                                            None,
+                                        )
+                                        .with_profession(Some(player.profession)),
+                                    );
+                                    self.log_ui.buffer.insert_squad_update_parts(&mut parts);
                                 } else {
-                                    parts.push(LogPart::new(
-                                        &player.character,
-                                        Some(&player.account),
-                                        None,
-                                        None,
-                                    ));
+                                    parts.push(
+                                        LogPart::new(
+                                            &player.character,
+                                            Some(&player.account),
+                                            None,
+                                            None,
+                                        )
+                                        .with_profession(Some(player.profession)),
+                                    );
                                     parts.push(LogPart::new_no_color(" has joined your instance"));
+                                    self.log_ui
+                                        .buffer
+                                        .insert_burst_parts(Burst::JoinedInstance, &mut parts);
                                 }
-                                self.log_ui.buffer.insert_squad_update_parts(&mut parts);
                             }
                         } else {
                             // agent removed
@@ -149,24 +191,34 @@ impl Plugin {
                                     parts.push(LogPart::new_no_color(
                                         "You have left an instance as ",
                                     ));
-                                    parts.push(LogPart::new(
-                                        &player.character,
-                                        Some(&player.account),
-                                        None,
-                                        None,
-                                    ));
+                                    parts.push(
+                                        LogPart::new(
+                                            &player.character,
+                                            Some(&player.account),
+                                            None,
+                                            None,
+                                        )
+                                        .with_profession(Some(player.profession)),
+                                    );
+                                    self.log_ui.buffer.insert_squad_update_parts(&mut parts);
                                 } else {
-                                    parts.push(LogPart::new(
-                                        &player.character,
-                                        Some(&player.account),
-                                        None,
-                                        None,
-                                    ));
+                                    parts.push(
+                                        LogPart::new(
+                                            &player.character,
+                                            Some(&player.account),
+                                            None,
+                                            None,
+                                        )
+                                        .with_profession(Some(player.profession)),
+                                    );
                                     parts.push(LogPart::new_no_color(" has left your instance"));
+                                    self.log_ui
+                                        .buffer
+                                        .insert_burst_parts(Burst::LeftInstance, &mut parts);
                                 }
-                                self.log_ui.buffer.insert_squad_update_parts(&mut parts);
                             }
                         }
+                        self.update_missing_members();
                     }
                 }
             }
@@ -174,6 +226,7 @@ impl Plugin {
     }
 
     pub fn squad_update(&mut self, users: UserInfoIter) {
+        let mut ready_changed = false;
         for user_update in users {
             let account_name = match user_update.account_name() {
                 Some(x) => arcdps::strip_account_prefix(x),
@@ -191,16 +244,7 @@ impl Plugin {
                     let old_info = self.tracker.add_extras_player(&owned_user);
                     match old_info {
                         Some(old_info) => {
-                            if user_update.ready_status && !old_info.ready_status {
-                                self.log_ui
-                                    .buffer
-                                    .insert_squad_update(format!("{} readied up", account_name));
-                            }
-                            if !user_update.ready_status && old_info.ready_status {
-                                self.log_ui
-                                    .buffer
-                                    .insert_squad_update(format!("{} unreadied", account_name));
-                            }
+                            ready_changed |= user_update.ready_status != old_info.ready_status;
                             if user_update.role != old_info.role {
                                 self.log_ui.buffer.insert_squad_update(format!(
                                     "{} changed roles from {} to {}",
@@ -250,5 +294,78 @@ impl Plugin {
                 UserRole::Invalid => {}
             };
         }
+        if ready_changed {
+            self.update_ready_check();
+        }
+        self.update_missing_members();
     }
+
+    /// Shows how many squad members are missing from our instance in the squad log title.
+    pub fn update_missing_members(&mut self) {
+        let missing = self.tracker.missing_members().len();
+        if missing == self.missing_members {
+            return;
+        }
+        self.missing_members = missing;
+        self.log_ui.name = match missing {
+            0 => LOG_WINDOW_TITLE.to_owned(),
+            missing => {
+                LOG_WINDOW_TITLE.replacen("###", &format!(" ({} not in instance)###", missing), 1)
+            }
+        };
+    }
+
+    /// Logs the ready check as one line, which is updated until the check is over.
+    fn update_ready_check(&mut self) {
+        let Some(check) = self.tracker.update_ready_check(Local::now()) else {
+            return;
+        };
+        let mut parts = summary_parts(check.summary());
+        let running = check.state == ReadyCheckState::Running;
+        let started = running && self.ready_check_line.is_none();
+        let buffer = &mut self.log_ui.buffer;
+        match self.ready_check_line {
+            Some(index) if buffer.replace_ready_check(index, &mut parts) => {}
+            _ => self.ready_check_line = Some(buffer.insert_ready_check(&mut parts)),
+        }
+        if !running {
+            self.ready_check_line = None;
+        }
+        if started && self.log_ui.settings.missing_members_on_ready_check {
+            self.log_missing_members();
+        }
+    }
+
+    /// Logs the squad members missing from our instance whenever the configured interval
+    /// has passed.
+    pub fn check_missing_members(&mut self) {
+        let interval = self.log_ui.settings.missing_members_interval;
+        if interval > 0
+            && self.missing_members_logged.elapsed() >= Duration::from_secs(interval as u64 * 60)
+        {
+            self.log_missing_members();
+        }
+    }
+
+    /// Logs the squad members who aren't in our instance, if there are any.
+    fn log_missing_members(&mut self) {
+        self.missing_members_logged = Instant::now();
+        let missing = self.tracker.missing_members();
+        if missing.is_empty() {
+            return;
+        }
+        let heading = format!("Not in your instance ({}): ", missing.len());
+        let mut parts = summary_parts(
+            std::iter::once((heading, None)).chain(ready_check::account_list(&missing)),
+        );
+        self.log_ui.buffer.insert_squad_update_parts(&mut parts);
+    }
+}
+
+/// Turns summary pieces into parts, with the names hovering as the accounts they are.
+fn summary_parts<'a>(summary: impl IntoIterator<Item = (String, Option<&'a str>)>) -> Vec<LogPart> {
+    summary
+        .into_iter()
+        .map(|(text, account_name)| LogPart::new(&text, account_name, None, None))
+        .collect()
 }
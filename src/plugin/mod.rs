@@ -3,7 +3,11 @@ mod settings;
 pub mod state;
 pub mod ui;
 
-use std::sync::{Arc, Mutex};
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
 
 use anyhow::Context;
 use arc_util::{
@@ -14,10 +18,14 @@ use arc_util::{
 use log::{error, info};
 
 use crate::{
+    chatlink::names::ChatLinkNames,
     db::ChatDatabase,
+    history::HistoryUi,
     logui::LogUi,
+    mentions::Mentions,
     notifications::Notifications,
     plugin::state::{MumbleLinkState, NotificationsState, TtsState},
+    roster::Roster,
     tracking::Tracker,
     tts::TextToSpeech,
     update::{self, UpdateState},
@@ -28,9 +36,14 @@ use self::state::UiState;
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 const SETTINGS_FILE: &str = "arcdps_chat_log.json";
+/// Title of the squad log window, with an id that stays the same when counts are added.
+const LOG_WINDOW_TITLE: &str = "Squad Log###Squad Log";
 
 pub struct Plugin {
     pub log_ui: Window<LogUi>,
+    pub history_ui: Window<HistoryUi>,
+    pub mentions: Window<Mentions>,
+    pub roster: Window<Roster>,
     pub notifications: Notifications,
     pub ui_state: UiState,
     pub self_account_name: String,
@@ -38,6 +51,12 @@ pub struct Plugin {
     chat_database: Option<Arc<Mutex<ChatDatabase>>>,
     tts: TextToSpeech,
     tracker: Tracker,
+    /// Index of the squad log line showing the running ready check.
+    ready_check_line: Option<u64>,
+    /// When the squad members missing from our instance were last logged.
+    missing_members_logged: Instant,
+    /// How many squad members the squad log title counts as missing.
+    missing_members: usize,
     pub update_state: UpdateState,
 }
 
@@ -45,7 +64,7 @@ impl Plugin {
     pub fn new() -> Self {
         Self {
             log_ui: Window::new(
-                "Squad Log",
+                LOG_WINDOW_TITLE,
                 LogUi::new(),
                 WindowOptions {
                     width: 500.0,
@@ -53,6 +72,33 @@ impl Plugin {
                     ..WindowOptions::new()
                 },
             ),
+            history_ui: Window::new(
+                "Chat History",
+                HistoryUi::new(),
+                WindowOptions {
+                    width: 600.0,
+                    height: 400.0,
+                    ..WindowOptions::new()
+                },
+            ),
+            mentions: Window::new(
+                "Mentions",
+                Mentions::new(),
+                WindowOptions {
+                    width: 400.0,
+                    height: 250.0,
+                    ..WindowOptions::new()
+                },
+            ),
+            roster: Window::new(
+                "Squad Roster",
+                Roster::new(),
+                WindowOptions {
+                    width: 600.0,
+                    height: 400.0,
+                    ..WindowOptions::new()
+                },
+            ),
             notifications: Notifications::new(),
             ui_state: UiState::new(),
             self_account_name: String::new(),
@@ -60,6 +106,9 @@ impl Plugin {
             chat_database: None,
             tts: TextToSpeech::new(),
             tracker: Tracker::new(),
+            ready_check_line: None,
+            missing_members_logged: Instant::now(),
+            missing_members: 0,
             update_state: UpdateState::new(
                 Some(update::get_current_version()),
                 update::get_dll_path().unwrap_or_default(),
@@ -73,6 +122,9 @@ impl Plugin {
         let mut settings = Settings::from_file(SETTINGS_FILE);
 
         settings.load_component(&mut self.log_ui);
+        settings.load_component(&mut self.history_ui);
+        settings.load_component(&mut self.mentions);
+        settings.load_component(&mut self.roster);
         settings.load_component(&mut self.notifications);
         settings.load_component(&mut self.tts);
         settings.load_component(&mut self.update_state);
@@ -87,16 +139,13 @@ impl Plugin {
         }
 
         self.log_ui.buffer.buffer_max_size = self.log_ui.settings.log_buffer as usize;
+        self.log_ui.buffer.collapse_repeats = self.log_ui.settings.collapse_repeats;
+        self.apply_repeat_window();
+        self.log_ui.buffer.group_bursts = self.log_ui.settings.group_bursts;
 
-        match ChatDatabase::try_new(&self.log_ui.settings.log_path, self.game_start)
-            .context("failed to init database")
-        {
-            Ok(chat_database) => {
-                self.chat_database = Some(Arc::new(Mutex::new(chat_database)));
-                self.log_ui.chat_database = self.chat_database.clone();
-            }
-            Err(err) => error!("{:#}", err),
-        }
+        self.open_database();
+        self.backfill_log();
+        self.load_chat_link_names();
 
         match self
             .notifications
@@ -131,13 +180,175 @@ impl Plugin {
         Ok(())
     }
 
+    /// Loads the latest messages from the database into the squad log, if enabled.
+    /// Roster updates aren't stored in the database, so only chat messages are restored.
+    fn backfill_log(&mut self) {
+        if self.log_ui.settings.backfill_messages <= 0 {
+            return;
+        }
+        let Some(chat_database) = &self.chat_database else {
+            return;
+        };
+        match chat_database
+            .lock()
+            .unwrap()
+            .recent_messages(self.log_ui.settings.backfill_messages as usize)
+        {
+            Ok(messages) => self.log_ui.buffer.backfill(&messages),
+            Err(err) => error!("failed to backfill squad log: {:#}", err),
+        }
+    }
+
+    fn open_database(&mut self) {
+        match ChatDatabase::try_new(
+            &self.log_ui.settings.log_path,
+            self.game_start,
+            &self.log_ui.settings.backup_settings,
+        )
+        .context("failed to init database")
+        {
+            Ok(chat_database) => {
+                self.chat_database = Some(Arc::new(Mutex::new(chat_database)));
+            }
+            Err(err) => {
+                self.chat_database = None;
+                error!("{:#}", err)
+            }
+        }
+        self.log_ui.chat_database = self.chat_database.clone();
+        self.roster.chat_database = self.chat_database.clone();
+        self.history_ui
+            .set_chat_database(self.chat_database.clone());
+    }
+
+    /// Closes the current database and opens the one at the configured path.
+    /// Pending inserts are flushed before the switch, and the current database
+    /// can optionally be copied to the new path if nothing exists there yet.
+    /// Falls back to the previous database if the copy fails or the new one fails to open.
+    /// Lines backfilled into the squad log are reloaded from whichever database is open.
+    pub fn switch_database(&mut self, copy_existing: bool) {
+        let old_path = self
+            .chat_database
+            .as_ref()
+            .map(|chat_database| chat_database.lock().unwrap().log_path.clone());
+        if let Some(chat_database) = self.chat_database.take() {
+            chat_database.lock().unwrap().release();
+        }
+        self.log_ui.chat_database = None;
+        self.roster.chat_database = None;
+        self.history_ui.set_chat_database(None);
+
+        info!("switching database to {}", self.log_ui.settings.log_path);
+        let mut copy_failed = false;
+        if let (true, Some(old_path)) = (copy_existing, &old_path) {
+            let new_path = &self.log_ui.settings.log_path;
+            if Path::new(new_path).exists() {
+                info!(
+                    "{} already exists, not copying the current database",
+                    new_path
+                );
+            } else if let Err(err) = ChatDatabase::copy_database(old_path, new_path)
+                .context("failed to copy existing database")
+            {
+                error!("{:#}", err);
+                copy_failed = true;
+            }
+        }
+        // an empty database at the new path would hide the history that failed to copy
+        if !copy_failed {
+            self.open_database();
+        }
+
+        if self.chat_database.is_none() {
+            if let Some(old_path) = old_path {
+                error!("reopening previous database {}", old_path);
+                self.log_ui.settings.log_path = old_path;
+                self.open_database();
+            }
+        }
+        self.log_ui.buffer.clear_history();
+        self.backfill_log();
+    }
+
+    /// Loads the bundled chat link names and the user's extra names, if any. The bundled
+    /// names are still used if the user's file can't be loaded.
+    pub fn load_chat_link_names(&mut self) {
+        let names = match ChatLinkNames::load(&self.log_ui.settings.chat_link_names_path) {
+            Ok(names) => {
+                self.ui_state.chat_link_names_status = Ok(names.len());
+                names
+            }
+            Err(err) => {
+                error!("{:#}", err);
+                self.ui_state.chat_link_names_status = Err(format!("{:#}", err));
+                ChatLinkNames::bundled()
+            }
+        };
+        let names = Arc::new(names);
+        self.tts.chat_link_names = names.clone();
+        self.log_ui.buffer.set_chat_link_names(names);
+    }
+
+    /// Rebuilds mention detection from our account and the characters seen on it, and
+    /// updates the squad log highlight if it changed.
+    pub fn update_mention_names(&mut self) {
+        let character_names = self
+            .tracker
+            .seen_users
+            .get(&self.self_account_name)
+            .into_iter()
+            .flatten()
+            .map(String::as_str);
+        if self
+            .mentions
+            .update_names(&self.self_account_name, character_names)
+        {
+            self.log_ui.set_mention_highlight(
+                self.mentions.pattern(),
+                self.mentions.settings.highlight_color,
+                &self.self_account_name,
+            );
+        }
+    }
+
+    /// Applies the repeat window setting to the squad log, notifications and text to speech.
+    pub fn apply_repeat_window(&mut self) {
+        let window = chrono::Duration::seconds(self.log_ui.settings.repeat_window.into());
+        self.log_ui.buffer.repeat_window = window;
+        self.notifications.repeats.window = window;
+        self.tts.repeats.window = window;
+    }
+
+    /// Forgets everything stored about an account, both in the database and in memory.
+    pub fn purge_account(&mut self, account_name: &str) {
+        info!("purging {}", account_name);
+        if let Some(chat_database) = &self.chat_database {
+            if let Err(err) = chat_database.lock().unwrap().purge_account(account_name) {
+                error!("failed to purge account: {:#}", err);
+            }
+        }
+        self.tracker.forget_user(account_name);
+        self.update_missing_members();
+        self.log_ui.buffer.purge_account(account_name);
+        // purging moves the lines after the removed ones
+        self.ready_check_line = None;
+        self.history_ui.clear_results();
+        self.mentions.purge_account(account_name);
+        self.notifications.repeats.forget(account_name);
+        self.tts.repeats.forget(account_name);
+    }
+
     pub fn release(&mut self) {
         self.update_state.finish_pending_tasks();
+        self.history_ui.release();
         if let Some(chat_database) = &self.chat_database {
             chat_database.lock().unwrap().release();
         }
         let mut settings = Settings::from_file(SETTINGS_FILE);
         settings.store_component(&self.log_ui);
+        settings.store_component(&self.history_ui);
+        settings.store_component(&self.mentions);
+        settings.store_component(&self.roster);
         settings.store_component(&self.notifications);
         settings.store_component(&self.tts);
         settings.store_component(&self.update_state);
@@ -117,11 +117,29 @@ impl Plugin {
                     "Enable chat logging to database",
                     &mut self.log_ui.settings.log_enabled,
                 );
-                ui.input_text(
-                    "Chat database path (not applied until restart)",
-                    &mut self.log_ui.settings.log_path,
-                )
-                .build();
+                ui.input_text("Chat database path", &mut self.log_ui.settings.log_path)
+                    .build();
+                let current_log_path = self
+                    .chat_database
+                    .as_ref()
+                    .map(|chat_database| chat_database.lock().unwrap().log_path.clone());
+                if current_log_path.as_deref() != Some(self.log_ui.settings.log_path.as_str()) {
+                    if ui.button("Apply database path") {
+                        self.switch_database(self.ui_state.copy_database_on_switch);
+                    }
+                    if current_log_path.is_some() {
+                        ui.same_line();
+                        ui.checkbox(
+                            "Copy existing data to the new path",
+                            &mut self.ui_state.copy_database_on_switch,
+                        );
+                        if ui.is_item_hovered() {
+                            ui.tooltip_text(
+                                "Only copies if there is no database at the new path yet",
+                            );
+                        }
+                    }
+                }
                 ui.set_next_item_width(input_width);
                 if ui
                     .input_int(
@@ -137,12 +155,293 @@ impl Plugin {
                         "This is the number of chat messages and squad updates to keep at a time in memory for this session",
                     );
                 }
+                if ui.checkbox(
+                    "Collapse repeated messages",
+                    &mut self.log_ui.settings.collapse_repeats,
+                ) {
+                    self.log_ui.buffer.collapse_repeats = self.log_ui.settings.collapse_repeats;
+                }
+                if ui.is_item_hovered() {
+                    ui.tooltip_text(
+                        "Shows a message an account repeats within the repeat window as one line with a count",
+                    );
+                }
+                ui.set_next_item_width(input_width);
+                if ui
+                    .input_int(
+                        "Seconds a repeated message counts as a repeat",
+                        &mut self.log_ui.settings.repeat_window,
+                    )
+                    .build()
+                {
+                    self.log_ui.settings.repeat_window = self.log_ui.settings.repeat_window.max(0);
+                    self.apply_repeat_window();
+                }
+                if ui.is_item_hovered() {
+                    ui.tooltip_text(
+                        "How long after an account's last message the same text is a repeat, for the squad log, notifications and text to speech",
+                    );
+                }
+                if ui.checkbox(
+                    "Group bursts of instance and combat updates",
+                    &mut self.log_ui.settings.group_bursts,
+                ) {
+                    self.log_ui.buffer.group_bursts = self.log_ui.settings.group_bursts;
+                }
+                if ui.is_item_hovered() {
+                    ui.tooltip_text(
+                        "Shows players joining or leaving your instance or combat within seconds of each other as one line, which can be expanded",
+                    );
+                }
+                ui.checkbox(
+                    "Log squad members not in your instance when a ready check starts",
+                    &mut self.log_ui.settings.missing_members_on_ready_check,
+                );
+                ui.set_next_item_width(input_width);
+                if ui
+                    .input_int(
+                        "Minutes between logging squad members not in your instance",
+                        &mut self.log_ui.settings.missing_members_interval,
+                    )
+                    .build()
+                {
+                    self.log_ui.settings.missing_members_interval =
+                        self.log_ui.settings.missing_members_interval.max(0);
+                }
+                if ui.is_item_hovered() {
+                    ui.tooltip_text("Set to 0 to only log them at ready checks");
+                }
+                ui.set_next_item_width(input_width);
+                ui.input_int(
+                    "Number of messages to load from the database on startup",
+                    &mut self.log_ui.settings.backfill_messages,
+                )
+                .build();
+                if ui.is_item_hovered() {
+                    ui.tooltip_text(
+                        "Previous messages are shown faded in the squad log. Set to 0 to start with an empty log",
+                    );
+                }
                 render::input_key(
                     ui,
                     "##chatloghotkey",
                     "Hotkey",
                     &mut self.log_ui.settings.hotkey,
                 );
+
+                ui.spacing();
+                ui.text_colored(grey, "Timestamps");
+                let timestamps = &mut self.log_ui.settings.timestamps;
+                ui.checkbox("24-hour clock", &mut timestamps.clock_24h);
+                ui.same_line();
+                ui.checkbox("Seconds", &mut timestamps.seconds);
+                ui.same_line();
+                ui.checkbox("Date", &mut timestamps.date);
+                ui.checkbox("Relative times", &mut timestamps.relative);
+                if ui.is_item_hovered() {
+                    ui.tooltip_text("Show how long ago each line was logged, like 2m ago");
+                }
+                ui.checkbox("Server time (UTC)", &mut timestamps.utc);
+                ui.checkbox("Day separators", &mut timestamps.day_separators);
+                if ui.is_item_hovered() {
+                    ui.tooltip_text("Show the date between lines logged on different days");
+                }
+
+                ui.spacing();
+                ui.text_colored(grey, "Backups");
+                let mut backup_settings_changed = false;
+                backup_settings_changed |= ui.checkbox(
+                    "Back up the database daily",
+                    &mut self.log_ui.settings.backup_settings.daily,
+                );
+                backup_settings_changed |= ui.checkbox(
+                    "Back up the database on game exit",
+                    &mut self.log_ui.settings.backup_settings.on_release,
+                );
+                ui.input_text(
+                    "Backup folder (blank for default)",
+                    &mut self.log_ui.settings.backup_settings.path,
+                )
+                .build();
+                backup_settings_changed |= ui.is_item_deactivated_after_edit();
+                ui.set_next_item_width(input_width);
+                if ui
+                    .input_int(
+                        "Number of backups to keep",
+                        &mut self.log_ui.settings.backup_settings.generations,
+                    )
+                    .build()
+                {
+                    self.log_ui.settings.backup_settings.generations =
+                        self.log_ui.settings.backup_settings.generations.max(1);
+                    backup_settings_changed = true;
+                }
+                if ui.is_item_hovered() {
+                    ui.tooltip_text("Older backups are deleted once this many have been taken");
+                }
+                if let Some(chat_database) = &self.chat_database {
+                    let chat_database = chat_database.lock().unwrap();
+                    if backup_settings_changed {
+                        if let Err(err) = chat_database
+                            .update_backup_settings(&self.log_ui.settings.backup_settings)
+                        {
+                            error!("failed to update backup settings: {:#}", err);
+                        }
+                    }
+                    if ui.button("Back up now") {
+                        if let Err(err) = chat_database.backup_now() {
+                            error!("failed to request backup: {:#}", err);
+                        }
+                    }
+                    let backup_status = chat_database.backup_status.lock().unwrap().clone();
+                    ui.same_line();
+                    if backup_status.in_progress {
+                        ui.text("Backing up...");
+                    } else if let Some(last_error) = &backup_status.last_error {
+                        ui.text_colored(red, format!("Backup failed: {}", last_error));
+                    } else if let Some(last_backup) = backup_status.last_backup {
+                        ui.text_colored(
+                            green,
+                            format!("Last backup: {}", last_backup.format("%Y-%m-%d %H:%M:%S")),
+                        );
+                    }
+                }
+                ui.text_disabled(format!(
+                    "Backups are stored in {}",
+                    self.log_ui
+                        .settings
+                        .backup_settings
+                        .backup_dir(&self.log_ui.settings.log_path)
+                        .display()
+                ));
+            }
+            if let Some(_tab) = ui.tab_item("Highlights") {
+                self.log_ui.render_highlight_settings(ui);
+            }
+            if let Some(_tab) = ui.tab_item("Chat links") {
+                ui.input_text(
+                    "Extra chat link names (blank for none)",
+                    &mut self.log_ui.settings.chat_link_names_path,
+                )
+                .hint("Path to a JSON file")
+                .build();
+                if ui.is_item_deactivated_after_edit() {
+                    self.load_chat_link_names();
+                }
+                if ui.is_item_hovered() {
+                    ui.tooltip_text(
+                        "Adds to the names shipped with the plugin, in the same format as data/chat_link_names.json",
+                    );
+                }
+                if ui.button("Reload names") {
+                    self.load_chat_link_names();
+                }
+                ui.same_line();
+                match &self.ui_state.chat_link_names_status {
+                    Ok(count) => {
+                        ui.text_colored(green, format!("{} names loaded", count));
+                        if let Some(build) = self.tts.chat_link_names.build {
+                            if ui.is_item_hovered() {
+                                ui.tooltip_text(format!(
+                                    "Bundled names are from game build {}",
+                                    build
+                                ));
+                            }
+                        }
+                    }
+                    Err(err) => ui.text_colored(red, err),
+                }
+            }
+            if let Some(_tab) = ui.tab_item("Mentions") {
+                let mut names_changed = false;
+                names_changed |= ui.checkbox(
+                    "Detect messages that mention you",
+                    &mut self.mentions.settings.enabled,
+                );
+                names_changed |= ui.checkbox(
+                    "Match your account name",
+                    &mut self.mentions.settings.match_account,
+                );
+                if ui.is_item_hovered() {
+                    ui.tooltip_text("Both the full account name and the part before the numbers");
+                }
+                names_changed |= ui.checkbox(
+                    "Match your character names",
+                    &mut self.mentions.settings.match_characters,
+                );
+                if ui.is_item_hovered() {
+                    ui.tooltip_text("Characters seen on your account this session");
+                }
+                ui.input_text("Nicknames", &mut self.mentions.settings.nicknames)
+                    .hint("Separated by commas")
+                    .build();
+                names_changed |= ui.is_item_deactivated_after_edit();
+                ui.color_edit4(
+                    "Highlight color",
+                    &mut self.mentions.settings.highlight_color,
+                );
+                if ui.is_item_deactivated_after_edit() {
+                    self.log_ui.set_mention_highlight(
+                        self.mentions.pattern(),
+                        self.mentions.settings.highlight_color,
+                        &self.self_account_name,
+                    );
+                }
+                if names_changed {
+                    self.update_mention_names();
+                }
+                match self.mentions.pattern() {
+                    Some(_) => ui.text_disabled(format!(
+                        "Currently matching: {}",
+                        self.mentions.names().join(", ")
+                    )),
+                    None => ui.text_disabled("Nothing to match yet"),
+                }
+
+                ui.spacing();
+                ui.text_colored(grey, "Alerts");
+                ui.checkbox(
+                    "Play a sound when mentioned",
+                    &mut self.notifications.settings.ping_on_mention,
+                );
+                if ui.is_item_hovered() {
+                    ui.tooltip_text("Uses the output device from the Notifications tab");
+                }
+                ui.set_next_item_width(input_width);
+                ui.slider(
+                    "Mention volume",
+                    0,
+                    100,
+                    &mut self.notifications.settings.mention_volume,
+                );
+                ui.input_text(
+                    "Mention sound path (blank for default)",
+                    &mut self.notifications.settings.mention_sound_path,
+                )
+                .build();
+                if ui.is_item_deactivated_after_edit() {
+                    if let Err(err) = self.notifications.update_mention_track() {
+                        error!("failed to update mention track: {:#}", err);
+                    }
+                }
+                ui.group(|| {
+                    ui.text("Status:");
+                    ui.same_line();
+                    if self.notifications.mention_track.is_valid() {
+                        ui.text_colored(green, &self.notifications.mention_track.status_message)
+                    } else {
+                        ui.text_colored(red, &self.notifications.mention_track.status_message)
+                    }
+                });
+                ui.checkbox(
+                    "Read mentions aloud with text to speech",
+                    &mut self.tts.settings.play_mentions,
+                );
+                if ui.is_item_hovered() {
+                    ui.tooltip_text(
+                        "Plays mentions even if other messages aren't played, using the voice from the TTS tab",
+                    );
+                }
             }
             if let Some(_tab) = ui.tab_item("Notifications") {
                 let audio_devices_guard = self.ui_state.audio_devices.lock().unwrap();
@@ -214,6 +513,15 @@ impl Plugin {
                     "Ping while out of combat",
                     &mut self.notifications.settings.ping_out_of_combat,
                 );
+                ui.checkbox(
+                    "Don't ping for repeated messages",
+                    &mut self.notifications.settings.skip_repeats,
+                );
+                if ui.is_item_hovered() {
+                    ui.tooltip_text(
+                        "Messages an account repeats within the repeat window, including mentions",
+                    );
+                }
 
                 ui.set_next_item_width(input_width);
                 ui.slider(
@@ -291,6 +599,15 @@ impl Plugin {
                 ) {
                     let _ = self.tts.update_settings();
                 }
+                ui.checkbox(
+                    "Skip repeated messages",
+                    &mut self.tts.settings.skip_repeats,
+                );
+                if ui.is_item_hovered() {
+                    ui.tooltip_text(
+                        "Messages an account repeats within the repeat window, including mentions",
+                    );
+                }
 
                 let cur_voice = self.tts.current_voice();
                 let mut new_voice_id = String::new();
@@ -397,6 +714,13 @@ impl Plugin {
     pub fn render_window_options(&mut self, ui: &Ui, option_name: Option<&str>) -> bool {
         if option_name.is_none() {
             ui.checkbox("Squad Log", self.log_ui.visible_mut());
+            ui.checkbox("Chat History", self.history_ui.visible_mut());
+            let mentions_label = match self.mentions.unread {
+                0 => "Mentions###mentions".to_owned(),
+                unread => format!("Mentions ({} new)###mentions", unread),
+            };
+            ui.checkbox(mentions_label, self.mentions.visible_mut());
+            ui.checkbox("Squad Roster", self.roster.visible_mut());
         }
         false
     }
@@ -51,6 +51,9 @@ pub struct UiState {
     pub notifications_state: NotificationsState,
     pub mumblelink_state: MumbleLinkState,
     pub tts_state: TtsState,
+    pub copy_database_on_switch: bool,
+    /// Number of chat link names loaded, or why loading them failed.
+    pub chat_link_names_status: Result<usize, String>,
     pub audio_devices: Arc<Mutex<Vec<String>>>,
     pub refreshing_audio_devices: Arc<Mutex<bool>>,
 }
@@ -62,6 +65,8 @@ impl UiState {
             notifications_state: NotificationsState::Unknown,
             mumblelink_state: MumbleLinkState::Unknown,
             tts_state: TtsState::Unknown,
+            copy_database_on_switch: false,
+            chat_link_names_status: Ok(0),
             audio_devices: Arc::new(Mutex::new(Vec::new())),
             refreshing_audio_devices: Arc::new(Mutex::new(false)),
         }
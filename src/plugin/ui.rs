@@ -5,7 +5,14 @@ use crate::update;
 
 impl Plugin {
     pub fn render_windows(&mut self, ui: &Ui, _not_loading: bool) {
+        self.check_missing_members();
         self.log_ui.render(ui, &self.tracker);
+        if let Some(account_name) = self.log_ui.purge_account.take() {
+            self.purge_account(&account_name);
+        }
+        self.history_ui.render(ui, &self.tracker);
+        self.mentions.render(ui, ());
+        self.roster.render(ui, &self.tracker);
         update::draw_update_window(ui, &mut self.update_state);
     }
 
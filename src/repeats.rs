@@ -0,0 +1,120 @@
+use std::collections::HashMap;
+
+use arcdps::extras::message::ChannelType;
+use chrono::{DateTime, Duration, Local};
+
+/// Default for how long after an account's last message the same text still counts as a
+/// repeat.
+pub const REPEAT_WINDOW: Duration = Duration::seconds(60);
+
+/// Reduces message text to its lowercase words, so messages that only differ in case,
+/// spacing or punctuation, like "STACK ON TAG!!" and "stack on tag", compare equal.
+pub fn normalize(text: &str) -> String {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Whether `time` is close enough to `last` for a message to count as repeating it.
+pub fn within_window(last: DateTime<Local>, time: DateTime<Local>, window: Duration) -> bool {
+    time - last <= window
+}
+
+/// The last message of an account, with the channel it was sent in.
+#[derive(Debug)]
+struct LastMessage {
+    channel_type: ChannelType,
+    subgroup: u8,
+    text: String,
+    time: DateTime<Local>,
+}
+
+/// Remembers the last message of each account, to tell when an account repeats itself.
+#[derive(Debug)]
+pub struct RepeatDetector {
+    last: HashMap<String, LastMessage>,
+    pub window: Duration,
+}
+
+impl Default for RepeatDetector {
+    fn default() -> Self {
+        Self {
+            last: HashMap::new(),
+            window: REPEAT_WINDOW,
+        }
+    }
+}
+
+impl RepeatDetector {
+    /// Records the message, returning whether it repeats the account's previous message
+    /// in the same channel and subgroup within the window. Each repeat extends the window.
+    pub fn is_repeat(
+        &mut self,
+        account_name: &str,
+        channel_type: ChannelType,
+        subgroup: u8,
+        text: &str,
+        time: DateTime<Local>,
+    ) -> bool {
+        let message = LastMessage {
+            channel_type,
+            subgroup,
+            text: normalize(text),
+            time,
+        };
+        let repeat = self.last.get(account_name).is_some_and(|last| {
+            last.channel_type == message.channel_type
+                && last.subgroup == message.subgroup
+                && last.text == message.text
+                && within_window(last.time, time, self.window)
+        });
+        self.last.insert(account_name.to_owned(), message);
+        repeat
+    }
+
+    /// Forgets the last message of the account.
+    pub fn forget(&mut self, account_name: &str) {
+        self.last.remove(account_name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("STACK  on tag!!"), "stack on tag");
+        assert_eq!(normalize("  -- pull in 5 --"), "pull in 5");
+        assert_eq!(normalize("!!!"), "");
+    }
+
+    #[test]
+    fn test_repeat_detector() {
+        let time = Local.with_ymd_and_hms(2024, 3, 10, 15, 0, 0).unwrap();
+        let mut repeats = RepeatDetector::default();
+        let mut is_repeat = |account_name, subgroup, text, seconds| {
+            let time = time + Duration::seconds(seconds);
+            repeats.is_repeat(account_name, ChannelType::Squad, subgroup, text, time)
+        };
+        assert!(!is_repeat("Foo.1234", 0, "stack on tag", 0));
+        assert!(!is_repeat("Baz.5678", 0, "stack on tag", 0));
+        assert!(is_repeat("Foo.1234", 0, "Stack on tag!", 50));
+        // the window starts again from the last repeat
+        assert!(is_repeat("Foo.1234", 0, "stack on tag", 100));
+        assert!(!is_repeat("Foo.1234", 0, "stack on tag", 200));
+        assert!(!is_repeat("Foo.1234", 0, "go", 201));
+        // the same text in another subgroup isn't a repeat
+        assert!(!is_repeat("Foo.1234", 2, "go", 202));
+        assert!(is_repeat("Foo.1234", 2, "go", 203));
+        assert!(!repeats.is_repeat("Foo.1234", ChannelType::Party, 2, "go", time));
+        repeats.forget("Foo.1234");
+        repeats.window = Duration::seconds(10);
+        assert!(!repeats.is_repeat("Foo.1234", ChannelType::Party, 0, "go", time));
+        let later = time + Duration::seconds(20);
+        assert!(!repeats.is_repeat("Foo.1234", ChannelType::Party, 0, "go", later));
+    }
+}
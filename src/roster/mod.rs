@@ -0,0 +1,87 @@
+use std::sync::{Arc, Mutex};
+
+use arc_util::tracking::Player;
+use arcdps::{extras::UserRole, Specialization};
+
+use crate::{db::ChatDatabase, tracking::SquadMember};
+
+use self::settings::RosterSettings;
+
+mod settings;
+mod ui;
+
+/// Lists the squad members from the tracker, grouped by subgroup.
+pub struct Roster {
+    pub settings: RosterSettings,
+    pub chat_database: Option<Arc<Mutex<ChatDatabase>>>,
+}
+
+impl Roster {
+    pub fn new() -> Self {
+        Self {
+            settings: RosterSettings::new(),
+            chat_database: None,
+        }
+    }
+}
+
+/// Sorts members by subgroup, then with the squad leader and lieutenants first.
+pub fn sort_members(members: &mut [SquadMember]) {
+    members.sort_by(|a, b| {
+        a.extras
+            .subgroup
+            .cmp(&b.extras.subgroup)
+            .then_with(|| role_rank(&a.extras.role).cmp(&role_rank(&b.extras.role)))
+            .then_with(|| {
+                a.account_name
+                    .to_lowercase()
+                    .cmp(&b.account_name.to_lowercase())
+            })
+    });
+}
+
+fn role_rank(role: &UserRole) -> u8 {
+    match role {
+        UserRole::SquadLeader => 0,
+        UserRole::Lieutenant => 1,
+        _ => 2,
+    }
+}
+
+/// The elite specialization of the player, or their profession without one.
+pub fn specialization_name(player: &Player) -> String {
+    match player.elite {
+        Specialization::Unknown => format!("{:?}", player.profession),
+        elite => format!("{:?}", elite),
+    }
+}
+
+/// How long ago the unix time `join_time` was, like "1h 05m", or nothing when extras
+/// didn't know when the member joined.
+pub fn format_joined(join_time: u64, now: i64) -> String {
+    if join_time == 0 {
+        return String::new();
+    }
+    let seconds = (now - join_time as i64).max(0);
+    match seconds {
+        0..=59 => format!("{}s", seconds),
+        60..=3599 => format!("{}m", seconds / 60),
+        _ => format!("{}h {:02}m", seconds / 3600, seconds / 60 % 60),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_joined() {
+        let now = 1_700_000_000;
+        assert_eq!(format_joined(0, now), "");
+        assert_eq!(format_joined(now as u64 - 42, now), "42s");
+        assert_eq!(format_joined(now as u64 - 600, now), "10m");
+        assert_eq!(format_joined(now as u64 - 4500, now), "1h 15m");
+        // clocks can disagree a little
+        assert_eq!(format_joined(now as u64 + 5, now), "0s");
+    }
+}
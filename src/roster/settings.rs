@@ -0,0 +1,43 @@
+use arc_util::settings::HasSettings;
+use serde::{Deserialize, Serialize};
+
+use super::Roster;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RosterSettings {
+    pub show_notes: bool,
+    pub show_joined: bool,
+    /// Only list the members who aren't in our instance.
+    pub only_missing: bool,
+}
+
+impl RosterSettings {
+    pub fn new() -> Self {
+        Self {
+            show_notes: true,
+            show_joined: true,
+            only_missing: false,
+        }
+    }
+}
+
+impl Default for RosterSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HasSettings for Roster {
+    type Settings = RosterSettings;
+
+    const SETTINGS_ID: &'static str = "roster";
+
+    fn current_settings(&self) -> Self::Settings {
+        self.settings.clone()
+    }
+
+    fn load_settings(&mut self, loaded: Self::Settings) {
+        self.settings = loaded;
+    }
+}
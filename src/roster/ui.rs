@@ -0,0 +1,162 @@
+use arc_util::ui::{
+    render::{self, item_context_menu},
+    Component, Ui, Windowable,
+};
+use arcdps::{
+    exports::{self, CoreColor},
+    imgui::StyleColor,
+};
+
+use crate::{db::query::QueriedNote, tracking::Tracker};
+
+use super::{format_joined, sort_members, specialization_name, Roster};
+
+impl Windowable<&Tracker> for Roster {
+    const CONTEXT_MENU: bool = true;
+    const DEFAULT_OPTIONS: bool = true;
+
+    fn render_menu(&mut self, ui: &Ui, _props: &mut &Tracker) {
+        ui.checkbox("Show time since joining", &mut self.settings.show_joined);
+        ui.checkbox("Show notes", &mut self.settings.show_notes);
+        ui.checkbox(
+            "Only show members not in your instance",
+            &mut self.settings.only_missing,
+        );
+        ui.separator();
+    }
+}
+
+impl Component<&Tracker> for Roster {
+    fn render(&mut self, ui: &Ui, tracker: &Tracker) {
+        let _style = render::small_padding(ui);
+        let colors = exports::colors();
+        let grey = colors
+            .core(CoreColor::MediumGrey)
+            .unwrap_or([0.5, 0.5, 0.5, 1.0]);
+        let green = colors
+            .core(CoreColor::LightGreen)
+            .unwrap_or([0.0, 1.0, 0.0, 1.0]);
+
+        let mut members: Vec<_> = tracker.squad_members().collect();
+        if members.is_empty() {
+            ui.text_disabled("You are not in a squad");
+            return;
+        }
+        sort_members(&mut members);
+        let in_instance = members.iter().filter(|member| member.arc.is_some()).count();
+        ui.text(format!(
+            "{} members, {} in your instance",
+            members.len(),
+            in_instance
+        ));
+        if self.settings.only_missing {
+            members.retain(|member| member.arc.is_none());
+            if members.is_empty() {
+                ui.text_disabled("Everyone is in your instance");
+                return;
+            }
+        }
+
+        let mut columns = vec![
+            "Role",
+            "Ready",
+            "Specialization",
+            "Character",
+            "Account",
+            "Instance",
+        ];
+        if self.settings.show_joined {
+            columns.push("Joined");
+        }
+        if self.settings.show_notes {
+            columns.push("Note");
+        }
+        let Some(_table) = ui.begin_table("roster", columns.len()) else {
+            return;
+        };
+        for column in &columns {
+            ui.table_setup_column(column);
+        }
+        ui.table_headers_row();
+
+        let now = chrono::Utc::now().timestamp();
+        let mut subgroup = None;
+        for member in members {
+            let extras = member.extras;
+            if subgroup != Some(extras.subgroup) {
+                subgroup = Some(extras.subgroup);
+                ui.table_next_row();
+                ui.table_next_column();
+                ui.text_colored(grey, format!("Subgroup {}", extras.subgroup + 1));
+            }
+            ui.table_next_row();
+
+            ui.table_next_column();
+            ui.text(extras.role.to_string());
+
+            ui.table_next_column();
+            if extras.ready_status {
+                ui.text_colored(green, "Ready");
+            }
+
+            ui.table_next_column();
+            if let Some(player) = member.arc {
+                ui.text(specialization_name(player));
+            }
+
+            ui.table_next_column();
+            match member.arc {
+                Some(player) => ui.text(&player.character),
+                None => ui.text_disabled("Unknown"),
+            }
+
+            let note = self.chat_database.as_ref().map(|chat_database| {
+                chat_database
+                    .lock()
+                    .unwrap()
+                    .get_or_query_note(member.account_name)
+            });
+            let note = match note {
+                Some(QueriedNote::Success(note)) => Some(note),
+                _ => None,
+            };
+            ui.table_next_column();
+            {
+                let _color_token = note.as_ref().and_then(|note| note.color).map(|color| {
+                    ui.push_style_color(StyleColor::Text, [color[0], color[1], color[2], 1.0])
+                });
+                ui.text(member.account_name);
+            }
+            item_context_menu(format!("##rostercontext{}", member.account_name), || {
+                if ui.button("Copy account name") {
+                    ui.set_clipboard_text(member.account_name);
+                    ui.close_current_popup();
+                }
+                if let Some(player) = member.arc {
+                    if ui.button("Copy character name") {
+                        ui.set_clipboard_text(&player.character);
+                        ui.close_current_popup();
+                    }
+                }
+            });
+
+            ui.table_next_column();
+            if member.arc.is_some() {
+                ui.text("Yes");
+            } else {
+                ui.text_disabled("No");
+            }
+
+            if self.settings.show_joined {
+                ui.table_next_column();
+                ui.text(format_joined(extras.join_time, now));
+            }
+            if self.settings.show_notes {
+                ui.table_next_column();
+                if let Some(note) = &note {
+                    ui.text(&note.note);
+                }
+            }
+        }
+    }
+}
@@ -2,11 +2,27 @@ use std::collections::{hash_map::Entry, HashMap, HashSet};
 
 use arc_util::tracking::Player;
 use arcdps::{
-    extras::{message::SquadMessage, UserInfoOwned},
+    extras::{message::SquadMessage, UserInfoOwned, UserRole},
     strip_account_prefix,
 };
+use chrono::{DateTime, Local};
 use log::debug;
 
+use self::ready_check::{ReadyCheck, ReadyCheckTracker};
+
+pub mod ready_check;
+
+/// Account display names shorter than this are too likely to be ordinary words to look
+/// for in message text.
+const MIN_DISPLAY_NAME_LENGTH: usize = 3;
+
+/// The part of an account name before the numbers, which people usually refer to each
+/// other by, if it is long enough to look for in message text.
+pub fn display_name(account_name: &str) -> Option<&str> {
+    let (display_name, _) = account_name.split_once('.')?;
+    (display_name.chars().count() >= MIN_DISPLAY_NAME_LENGTH).then_some(display_name)
+}
+
 #[derive(Debug)]
 pub struct PlayerInfo {
     pub arc: Option<Player>,
@@ -27,11 +43,21 @@ impl PlayerInfo {
     }
 }
 
+/// A squad member, with their arc data if they are in our instance.
+#[derive(Debug, Clone, Copy)]
+pub struct SquadMember<'a> {
+    /// Account name without the `:` prefix.
+    pub account_name: &'a str,
+    pub extras: &'a UserInfoOwned,
+    pub arc: Option<&'a Player>,
+}
+
 #[derive(Debug)]
 pub struct Tracker {
     pub map: HashMap<String, PlayerInfo>,
     pub arc_id_map: HashMap<usize, String>,
     pub seen_users: HashMap<String, HashSet<String>>,
+    ready_check: ReadyCheckTracker,
 }
 
 impl Tracker {
@@ -40,6 +66,7 @@ impl Tracker {
             map: HashMap::new(),
             arc_id_map: HashMap::new(),
             seen_users: HashMap::new(),
+            ready_check: ReadyCheckTracker::default(),
         }
     }
 
@@ -160,7 +187,60 @@ impl Tracker {
         };
     }
 
+    /// Removes an account from the seen users list.
+    pub fn forget_user(&mut self, account_name: &str) {
+        self.seen_users.remove(account_name);
+    }
+
+    /// Everyone in the squad, including those who aren't in our instance.
+    pub fn squad_members(&self) -> impl Iterator<Item = SquadMember<'_>> {
+        squad_members(&self.map)
+    }
+
+    /// Account names of the squad members who aren't in our instance, sorted. Empty when
+    /// no squad member is in our instance, like while loading into a map.
+    pub fn missing_members(&self) -> Vec<&str> {
+        let mut in_instance = false;
+        let mut missing = Vec::new();
+        for member in self.squad_members() {
+            match member.arc {
+                Some(_) => in_instance = true,
+                None => missing.push(member.account_name),
+            }
+        }
+        if !in_instance {
+            return Vec::new();
+        }
+        missing.sort_unstable_by_key(|account_name| account_name.to_lowercase());
+        missing
+    }
+
+    /// Updates the ready check from the ready status of the squad members, returning it
+    /// if it changed.
+    pub fn update_ready_check(&mut self, time: DateTime<Local>) -> Option<&ReadyCheck> {
+        let members = squad_members(&self.map)
+            .map(|member| (member.account_name, member.extras.ready_status));
+        self.ready_check.update(members, time)
+    }
+
     pub fn clear(&mut self) {
-        self.map.clear()
+        self.map.clear();
+        self.ready_check.clear();
     }
 }
+
+fn squad_members(map: &HashMap<String, PlayerInfo>) -> impl Iterator<Item = SquadMember<'_>> {
+    map.values().filter_map(|info| {
+        let extras = info.extras.as_ref()?;
+        let account_name = extras.account_name.as_deref()?;
+        matches!(
+            extras.role,
+            UserRole::SquadLeader | UserRole::Lieutenant | UserRole::Member
+        )
+        .then(|| SquadMember {
+            account_name: strip_account_prefix(account_name),
+            extras,
+            arc: info.arc.as_ref(),
+        })
+    })
+}
@@ -0,0 +1,231 @@
+use chrono::{DateTime, Duration, Local};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReadyCheckState {
+    Running,
+    /// Everyone readied, after the duration.
+    Completed(Duration),
+    /// Everyone was unreadied again before the check completed, after the duration.
+    Ended(Duration),
+}
+
+/// A ready check, as followed from the ready status of the squad members.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReadyCheck {
+    pub started: DateTime<Local>,
+    /// Who readied first, which is usually whoever started the check.
+    pub started_by: String,
+    pub ready: Vec<String>,
+    pub not_ready: Vec<String>,
+    pub state: ReadyCheckState,
+}
+
+impl ReadyCheck {
+    /// The summary as pieces of text, each with the account it names if it is a name.
+    pub fn summary(&self) -> Vec<(String, Option<&str>)> {
+        let mut summary = vec![
+            ("Ready check by ".to_owned(), None),
+            (self.started_by.clone(), Some(self.started_by.as_str())),
+        ];
+        match self.state {
+            ReadyCheckState::Running => {
+                summary.push((
+                    format!(
+                        ": {}/{} ready, waiting for ",
+                        self.ready.len(),
+                        self.ready.len() + self.not_ready.len()
+                    ),
+                    None,
+                ));
+                summary.extend(account_list(&self.not_ready));
+            }
+            ReadyCheckState::Completed(duration) => summary.push((
+                format!(": everyone was ready after {}", format_duration(duration)),
+                None,
+            )),
+            ReadyCheckState::Ended(duration) => {
+                summary.push((
+                    format!(" ended after {}, not ready: ", format_duration(duration)),
+                    None,
+                ));
+                summary.extend(account_list(&self.not_ready));
+            }
+        }
+        summary
+    }
+}
+
+/// The accounts separated by commas, as summary pieces.
+pub fn account_list<S: AsRef<str>>(
+    account_names: &[S],
+) -> impl Iterator<Item = (String, Option<&str>)> {
+    account_names
+        .iter()
+        .enumerate()
+        .flat_map(|(i, account_name)| {
+            let account_name = account_name.as_ref();
+            let separator = (i > 0).then(|| (", ".to_owned(), None));
+            separator
+                .into_iter()
+                .chain([(account_name.to_owned(), Some(account_name))])
+        })
+}
+
+/// Follows ready checks from changes in the ready status of squad members. A check
+/// starts when someone readies, completes when everyone is ready, and ends early when
+/// everyone is unreadied again, like when it times out.
+#[derive(Debug, Default)]
+pub struct ReadyCheckTracker {
+    check: Option<ReadyCheck>,
+}
+
+impl ReadyCheckTracker {
+    /// Updates the check from the ready status of every squad member, returning it if
+    /// it changed.
+    pub fn update<'a>(
+        &mut self,
+        members: impl IntoIterator<Item = (&'a str, bool)>,
+        time: DateTime<Local>,
+    ) -> Option<&ReadyCheck> {
+        let (ready, not_ready): (Vec<_>, Vec<_>) =
+            members.into_iter().partition(|(_, ready)| *ready);
+        let mut ready: Vec<String> = ready.into_iter().map(|(name, _)| name.to_owned()).collect();
+        let mut not_ready: Vec<String> = not_ready
+            .into_iter()
+            .map(|(name, _)| name.to_owned())
+            .collect();
+        ready.sort_unstable();
+        not_ready.sort_unstable();
+
+        match &mut self.check {
+            Some(check) if check.state == ReadyCheckState::Running => {
+                if ready == check.ready && not_ready == check.not_ready {
+                    return None;
+                }
+                if not_ready.is_empty() {
+                    check.state = ReadyCheckState::Completed(time - check.started);
+                    check.ready = ready;
+                    check.not_ready = not_ready;
+                } else if ready.is_empty() {
+                    // keep who was missing from before everyone was unreadied
+                    check.state = ReadyCheckState::Ended(time - check.started);
+                } else {
+                    check.ready = ready;
+                    check.not_ready = not_ready;
+                }
+            }
+            Some(ReadyCheck {
+                state: ReadyCheckState::Completed(_),
+                ..
+            }) if !ready.is_empty() => {
+                // statuses are reset after a check completes, before another can start
+                return None;
+            }
+            _ => {
+                let Some(started_by) = ready.first().cloned() else {
+                    self.check = None;
+                    return None;
+                };
+                let state = if not_ready.is_empty() {
+                    ReadyCheckState::Completed(Duration::zero())
+                } else {
+                    ReadyCheckState::Running
+                };
+                self.check = Some(ReadyCheck {
+                    started: time,
+                    started_by,
+                    ready,
+                    not_ready,
+                    state,
+                });
+            }
+        }
+        self.check.as_ref()
+    }
+
+    pub fn clear(&mut self) {
+        self.check = None;
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.num_seconds().max(0);
+    if seconds < 60 {
+        format!("{}s", seconds)
+    } else {
+        format!("{}m {}s", seconds / 60, seconds % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn text(check: &ReadyCheck) -> String {
+        check.summary().into_iter().map(|(text, _)| text).collect()
+    }
+
+    #[test]
+    fn test_ready_check() {
+        let time = Local.with_ymd_and_hms(2024, 3, 10, 15, 0, 0).unwrap();
+        let mut tracker = ReadyCheckTracker::default();
+        assert!(tracker
+            .update([("Foo.1234", false), ("Baz.5678", false)], time)
+            .is_none());
+
+        let check = tracker
+            .update(
+                [("Foo.1234", true), ("Baz.5678", false), ("Qux.9012", false)],
+                time,
+            )
+            .unwrap();
+        assert_eq!(check.state, ReadyCheckState::Running);
+        assert_eq!(
+            text(check),
+            "Ready check by Foo.1234: 1/3 ready, waiting for Baz.5678, Qux.9012"
+        );
+        let accounts: Vec<_> = check.summary().into_iter().filter_map(|(_, a)| a).collect();
+        assert_eq!(accounts, ["Foo.1234", "Baz.5678", "Qux.9012"]);
+
+        let check = tracker
+            .update(
+                [("Foo.1234", true), ("Baz.5678", true), ("Qux.9012", true)],
+                time + Duration::seconds(75),
+            )
+            .unwrap();
+        assert_eq!(
+            text(check),
+            "Ready check by Foo.1234: everyone was ready after 1m 15s"
+        );
+        // statuses resetting after the check isn't a new check
+        assert!(tracker
+            .update(
+                [("Foo.1234", false), ("Baz.5678", true), ("Qux.9012", true)],
+                time
+            )
+            .is_none());
+        assert!(tracker
+            .update(
+                [
+                    ("Foo.1234", false),
+                    ("Baz.5678", false),
+                    ("Qux.9012", false)
+                ],
+                time
+            )
+            .is_none());
+
+        tracker.update([("Baz.5678", true), ("Qux.9012", false)], time);
+        let check = tracker
+            .update(
+                [("Baz.5678", false), ("Qux.9012", false)],
+                time + Duration::seconds(30),
+            )
+            .unwrap();
+        assert_eq!(
+            text(check),
+            "Ready check by Baz.5678 ended after 30s, not ready: Qux.9012"
+        );
+    }
+}
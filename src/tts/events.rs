@@ -1,25 +1,66 @@
-use crate::MUMBLE_LINK;
+use crate::{
+    chatlink::{self, names::ChatLinkNames},
+    MUMBLE_LINK,
+};
 use arcdps::extras::{message::SquadMessageFlags, ChannelType};
 
 use super::TextToSpeech;
 use arcdps::extras::message::SquadMessageOwned;
+use chrono::Local;
 use log::error;
-use regex::Regex;
 
 impl TextToSpeech {
-    pub fn process_message(&mut self, message: &SquadMessageOwned, self_account_name: &str) {
+    pub fn process_message(
+        &mut self,
+        message: &SquadMessageOwned,
+        self_account_name: &str,
+        mentioned: bool,
+    ) {
+        let repeat = self.repeats.is_repeat(
+            &message.account_name,
+            message.channel_type,
+            message.subgroup,
+            &message.text,
+            Local::now(),
+        );
+        if repeat && self.settings.skip_repeats {
+            return;
+        }
         if !self.settings.play_on_self_message && message.account_name == self_account_name {
             return;
         }
-        if !self.settings.play_on_all_new_messages {
+        if !(mentioned && self.settings.play_mentions) && !self.play_for_channel(message) {
             return;
         }
+        match MUMBLE_LINK.lock().unwrap().tick() {
+            Some(linked_mem) => {
+                if linked_mem.context.is_in_combat() && !self.settings.play_in_combat {
+                    return;
+                }
+                if !linked_mem.context.is_in_combat() && !self.settings.play_out_of_combat {
+                    return;
+                }
+            }
+            None => {
+                return;
+            }
+        };
+        self.play(&Self::sanitize_message(
+            &message.text,
+            &self.chat_link_names,
+        ));
+    }
+
+    fn play_for_channel(&self, message: &SquadMessageOwned) -> bool {
+        if !self.settings.play_on_all_new_messages {
+            return false;
+        }
         if !self.settings.play_party_messages {
             if message.channel_type == ChannelType::Party {
-                return;
+                return false;
             }
             if message.channel_type == ChannelType::Squad && message.subgroup != 255 {
-                return;
+                return false;
             }
         }
         if !self.settings.play_squad_messages
@@ -27,27 +68,14 @@ impl TextToSpeech {
             && message.subgroup == 255
             && !message.flags.contains(SquadMessageFlags::IS_BROADCAST)
         {
-            return;
+            return false;
         }
         if message.flags.contains(SquadMessageFlags::IS_BROADCAST)
             && !self.settings.play_squad_broadcasts
         {
-            return;
+            return false;
         }
-        match MUMBLE_LINK.lock().unwrap().tick() {
-            Some(linked_mem) => {
-                if linked_mem.context.is_in_combat() && !self.settings.play_in_combat {
-                    return;
-                }
-                if !linked_mem.context.is_in_combat() && !self.settings.play_out_of_combat {
-                    return;
-                }
-            }
-            None => {
-                return;
-            }
-        };
-        self.play(&Self::sanitize_message(&message.text));
+        true
     }
 
     pub fn play(&mut self, text: &str) {
@@ -61,36 +89,53 @@ impl TextToSpeech {
         }
     }
 
-    fn sanitize_message(message: &str) -> String {
-        // Clean up chat codes until we have an implementation to strip it
-        let re = Regex::new(r"\[&[A-Za-z0-9+/=]+\]").unwrap();
-        re.replace_all(message, "chatcode").to_string()
+    /// Replaces chat codes with the name of what they link to, or "chatcode" if it isn't known.
+    fn sanitize_message(message: &str, names: &ChatLinkNames) -> String {
+        let mut sanitized = String::with_capacity(message.len());
+        let mut end = 0;
+        for (range, link) in chatlink::find_chat_links(message) {
+            sanitized.push_str(&message[end..range.start]);
+            match link.and_then(|link| names.name(&link)) {
+                Some(name) => sanitized.push_str(&name),
+                None => sanitized.push_str("chatcode"),
+            }
+            end = range.end;
+        }
+        sanitized.push_str(&message[end..]);
+        sanitized
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::TextToSpeech;
+    use crate::chatlink::names::ChatLinkNames;
 
     #[test]
     fn sanitize_message() {
+        let names = ChatLinkNames::bundled();
         assert_eq!(
             "this message has no chat codes",
-            TextToSpeech::sanitize_message("this message has no chat codes")
+            TextToSpeech::sanitize_message("this message has no chat codes", &names)
         );
         assert_eq!(
             "incomplete chat code [asdasdsa]",
-            TextToSpeech::sanitize_message("incomplete chat code [asdasdsa]")
+            TextToSpeech::sanitize_message("incomplete chat code [asdasdsa]", &names)
         );
         assert_eq!(
             "single chatcode chatcode",
-            TextToSpeech::sanitize_message("single chatcode [&AgGqtgAA]")
+            TextToSpeech::sanitize_message("single chatcode [&AgGqtgAA]", &names)
         );
         assert_eq!(
             "multiple chatcode here chatcode and here chatcode",
             TextToSpeech::sanitize_message(
-                "multiple chatcode here [&AgGqtgAA] and here [&AgGqtgAA]"
+                "multiple chatcode here [&AgGqtgAA] and here [&AgGqtgAA]",
+                &names
             )
         );
+        assert_eq!(
+            "bring 250 Glob of Ectoplasm",
+            TextToSpeech::sanitize_message("bring [&AvoJTQBA6l8AAA==]", &names)
+        );
     }
 }
@@ -1,6 +1,10 @@
+use std::sync::Arc;
+
 use anyhow::Context;
 use tts::{Tts, Voice};
 
+use crate::{chatlink::names::ChatLinkNames, repeats::RepeatDetector};
+
 use self::settings::TextToSpeechSettings;
 
 mod events;
@@ -10,6 +14,9 @@ pub struct TextToSpeech {
     pub settings: TextToSpeechSettings,
     pub tts: Option<Tts>,
     voice_cache: Option<Vec<Voice>>,
+    /// Used to read out what chat links point at.
+    pub chat_link_names: Arc<ChatLinkNames>,
+    pub repeats: RepeatDetector,
 }
 
 impl TextToSpeech {
@@ -18,6 +25,8 @@ impl TextToSpeech {
             settings: TextToSpeechSettings::new(),
             tts: None,
             voice_cache: None,
+            chat_link_names: Arc::new(ChatLinkNames::bundled()),
+            repeats: RepeatDetector::default(),
         }
     }
 
@@ -23,6 +23,10 @@ pub struct TextToSpeechSettings {
     pub play_squad_broadcasts: bool,
     #[serde(default = "default_as_true")]
     pub play_party_messages: bool,
+    /// Play messages that mention us, even if other messages aren't played.
+    pub play_mentions: bool,
+    /// Skip messages an account repeats, see [`crate::repeats`].
+    pub skip_repeats: bool,
 }
 
 fn default_as_true() -> bool {
@@ -45,6 +49,8 @@ impl TextToSpeechSettings {
             play_squad_messages: true,
             play_squad_broadcasts: true,
             play_party_messages: true,
+            play_mentions: false,
+            skip_repeats: false,
         }
     }
 }
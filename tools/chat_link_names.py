@@ -0,0 +1,93 @@
+#!/usr/bin/env python3
+"""Regenerates data/chat_link_names.json from the official Guild Wars 2 API.
+
+The bundled names are a subset of the game's data, kept small since they are compiled
+into the plugin:
+
+- common crafting materials, from the ITEMS list below
+- every waypoint in the main cities, from the MAPS list below
+- every profession and weapon skill listed by /v2/professions
+
+Run from the repository root with `python3 tools/chat_link_names.py`. Only the standard
+library is needed. The game build the names were read from is written to the file.
+"""
+
+import json
+import os
+import urllib.request
+
+API = "https://api.guildwars2.com/v2"
+OUTPUT = os.path.join(os.path.dirname(__file__), "..", "data", "chat_link_names.json")
+FORMAT_VERSION = 2
+# the API returns at most 200 entries per request
+PAGE_SIZE = 200
+
+ITEMS = [
+    19663, 19675, 19676, 19680, 19682, 19683, 19684, 19685, 19686, 19687, 19688, 19697,
+    19698, 19699, 19700, 19701, 19702, 19703, 19709, 19712, 19721, 19722, 19725, 19729,
+    19732, 19737, 19745, 19746, 19747, 19748, 19750, 19924, 19925, 19976, 20796, 20799,
+    24276, 24277, 24283, 24289, 24295, 24300, 24351, 24357, 24358, 36041, 46731, 46733,
+    46735, 46736, 46738, 46739, 46741, 68063, 70820, 71581, 73248, 89140, 89271,
+]
+
+# Divinity's Reach, Lion's Arch, The Grove, Rata Sum, Black Citadel, Hoelbrak
+MAPS = [18, 50, 91, 139, 218, 326]
+
+
+def get(path):
+    with urllib.request.urlopen(f"{API}/{path}") as response:
+        return json.load(response)
+
+
+def get_many(endpoint, ids):
+    ids = sorted(set(ids))
+    results = []
+    for start in range(0, len(ids), PAGE_SIZE):
+        page = ",".join(str(id) for id in ids[start : start + PAGE_SIZE])
+        results.extend(get(f"{endpoint}?ids={page}"))
+    return results
+
+
+def names(entries):
+    return {str(entry["id"]): entry["name"] for entry in entries if entry.get("name")}
+
+
+def waypoints():
+    found = {}
+    for map_info in get_many("maps", MAPS):
+        path = "continents/{}/floors/{}/regions/{}/maps/{}".format(
+            map_info["continent_id"],
+            map_info["default_floor"],
+            map_info["region_id"],
+            map_info["id"],
+        )
+        for point in get(path)["points_of_interest"].values():
+            if point["type"] == "waypoint" and point.get("name"):
+                found[str(point["id"])] = f"{point['name']} ({map_info['name']})"
+    return found
+
+
+def profession_skills():
+    ids = []
+    for profession in get("professions?ids=all"):
+        ids.extend(skill["id"] for skill in profession["skills"])
+        for weapon in profession["weapons"].values():
+            ids.extend(skill["id"] for skill in weapon["skills"])
+    return names(get_many("skills", ids))
+
+
+def main():
+    data = {
+        "version": FORMAT_VERSION,
+        "build": get("build")["id"],
+        "items": names(get_many("items", ITEMS)),
+        "map": waypoints(),
+        "skills": profession_skills(),
+    }
+    with open(OUTPUT, "w", encoding="utf-8", newline="\n") as file:
+        json.dump(data, file, indent=2, ensure_ascii=False, sort_keys=True)
+        file.write("\n")
+
+
+if __name__ == "__main__":
+    main()